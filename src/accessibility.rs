@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::controls::Controls;
+use crate::locale::{self, Localization};
+use crate::render::{Palette, SnakeGlyphs, Theme};
+use crate::ui::draw_row;
+use crate::{GameSettings, GameState};
+
+// Shares of the start speed that can be picked, slowest last
+pub const SPEED_FLOORS: [f32; 4] = [1.0, 0.8, 0.6, 0.4];
+
+// Rows of the accessibility screen, named under `accessibility` in the language files
+const ROWS: [&str; 4] = ["shapes", "high_contrast", "speed_floor", "announce"];
+
+// Settings from the accessibility screen, which is opened from the options and saved with
+// them
+pub struct Accessibility {
+    // Everything on the board that's otherwise told apart by color alone gets a glyph of
+    // its own: each kind of food, the two kinds of hazard, the ghost and the second snake
+    pub shapes: bool,
+    // Share of the start speed the snakes set off at, and come back to on each new level
+    pub speed_floor: f32,
+    // What happens in the game is described on a log line along the bottom, see `announce`
+    pub announce: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            shapes: false,
+            speed_floor: SPEED_FLOORS[0],
+            announce: false,
+        }
+    }
+}
+
+// Glyphs for the things `render` draws in more than one color
+pub struct Shapes {
+    pub food: char,
+    pub bonus: char,
+    pub poison: char,
+    pub rotten: char,
+    // Outside the arena and about to be
+    pub hazard: char,
+    pub closing: char,
+    pub ghost: char,
+}
+
+// Fixed rather than read from `snake.toml`, which could give two things the same glyph
+const SHAPES: Shapes = Shapes {
+    food: '☼',
+    bonus: '♦',
+    poison: '!',
+    rotten: '%',
+    hazard: '▓',
+    closing: '░',
+    ghost: '·',
+};
+
+impl Accessibility {
+    pub fn shapes(&self, settings: &GameSettings) -> Shapes {
+        match self.shapes {
+            true => SHAPES,
+            false => Shapes {
+                food: settings.food_glyph,
+                bonus: settings.bonus_glyph,
+                poison: settings.poison_glyph,
+                rotten: settings.rotten_glyph,
+                hazard: settings.hazard_glyph,
+                closing: settings.hazard_glyph,
+                ghost: settings.body_glyph,
+            },
+        }
+    }
+
+    // Double lines for the first snake and single lines for the second, even when the theme
+    // draws solid blocks
+    pub fn snake_glyphs(&self, player: usize, theme: &Theme) -> Option<SnakeGlyphs> {
+        match (self.shapes, player) {
+            (false, _) => theme.snake_glyphs,
+            (true, 0) => Some(SnakeGlyphs::double_line()),
+            (true, _) => Some(SnakeGlyphs::single_line()),
+        }
+    }
+
+    fn step_speed_floor(&mut self, step: i32) {
+        let i = SPEED_FLOORS
+            .iter()
+            .position(|floor| *floor == self.speed_floor)
+            .unwrap_or(0) as i32;
+        let len = SPEED_FLOORS.len() as i32;
+        self.speed_floor = SPEED_FLOORS[(i + step).rem_euclid(len) as usize];
+    }
+}
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Accessibility).with_system(accessibility_menu),
+        );
+    }
+}
+
+// Works like the options screen, which saves these once it's left
+fn accessibility_menu(
+    mut controls: Controls,
+    input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    mut selected: Local<usize>,
+    mut accessibility: ResMut<Accessibility>,
+    mut theme: ResMut<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        state.pop().unwrap();
+        return;
+    }
+
+    let dir = controls.dir() + controls.second_dir();
+    let len = ROWS.len() as i32;
+    *selected = (*selected as i32 - dir.y).rem_euclid(len) as usize;
+    if dir.x != 0 || controls.confirm() {
+        match ROWS[*selected] {
+            "shapes" => accessibility.shapes = !accessibility.shapes,
+            "announce" => accessibility.announce = !accessibility.announce,
+            // Picking it again goes back to the default palette
+            "high_contrast" => {
+                theme.palette = match theme.palette {
+                    Palette::HighContrast => Palette::default(),
+                    _ => Palette::HighContrast,
+                };
+                theme.body_colors = theme.palette.body_colors();
+            }
+            // Confirm goes on to the next one like right does
+            _ => accessibility.step_speed_floor(match dir.x {
+                0 => 1,
+                x => x,
+            }),
+        }
+    }
+
+    let values = [
+        loc.on_off(accessibility.shapes),
+        loc.on_off(theme.palette == Palette::HighContrast),
+        format!("{}%", (accessibility.speed_floor * 100.0).round()),
+        loc.on_off(accessibility.announce),
+    ];
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let colors = theme.colors();
+    let title = loc.get("accessibility.title");
+    term.put_string(
+        [locale::centered(&title), 6].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    for (i, (id, value)) in ROWS.iter().zip(values).enumerate() {
+        let name = loc.get(&format!("accessibility.{}", id));
+        let line = format!("{}: {}", name, value);
+        let y = 3 - i as i32;
+        draw_row(&mut term, y, &line, i == *selected, colors);
+    }
+    let help = loc.get("options.help");
+    term.put_string(
+        [locale::centered(&help), -5].pivot(Pivot::Center),
+        help.fg(colors.faded),
+    );
+    let back = loc.get("common.back");
+    term.put_string(
+        [locale::centered(&back), -6].pivot(Pivot::Center),
+        back.fg(colors.faded),
+    );
+}
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::controls::Controls;
+use crate::high_score;
+use crate::locale::{self, Localization};
+use crate::render::Theme;
+use crate::replay::Replay;
+use crate::{GameSettings, GameState, RunStats};
+
+const FILE_NAME: &str = "achievements";
+const TOAST_TIME: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    FirstDeath,
+    TenFood,
+    TopSpeed,
+    FiveMinutes,
+    QuarterBoard,
+}
+
+impl Achievement {
+    const ALL: [Achievement; 5] = [
+        Achievement::FirstDeath,
+        Achievement::TenFood,
+        Achievement::TopSpeed,
+        Achievement::FiveMinutes,
+        Achievement::QuarterBoard,
+    ];
+
+    fn name(self, loc: &Localization) -> String {
+        loc.name("achievements.name", self)
+    }
+
+    fn description(self, loc: &Localization) -> String {
+        loc.name("achievements.description", self)
+    }
+}
+
+// Milestones reached so far, kept next to the high scores
+#[derive(Default)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    // Unlocked but not shown yet, the first one is on screen
+    toasts: VecDeque<Achievement>,
+}
+
+impl Achievements {
+    fn unlock(&mut self, achievement: Achievement) {
+        if !self.unlocked.contains(&achievement) {
+            self.unlocked.push(achievement);
+            self.toasts.push_back(achievement);
+        }
+    }
+
+    // One debug name per line
+    fn to_text(&self) -> String {
+        let names: Vec<_> = self.unlocked.iter().map(|a| format!("{:?}", a)).collect();
+        names.join("\n")
+    }
+
+    fn from_text(text: &str) -> Self {
+        let unlocked = text
+            .lines()
+            .filter_map(|line| {
+                Achievement::ALL
+                    .into_iter()
+                    .find(|a| format!("{:?}", a) == line.trim())
+            })
+            .collect();
+        Self {
+            unlocked,
+            ..default()
+        }
+    }
+}
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        let achievements = high_score::load(FILE_NAME)
+            .map(|text| Achievements::from_text(&text))
+            .unwrap_or_default();
+        app.insert_resource(achievements)
+            .add_system(save_achievements)
+            .add_system_to_stage(CoreStage::PostUpdate, toast)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing).with_system(check_achievements),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(first_death))
+            .add_system_set(SystemSet::on_update(GameState::Title).with_system(open_achievements))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Achievements).with_system(render_achievements),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Achievements).with_system(close_achievements),
+            );
+    }
+}
+
+fn save_achievements(achievements: Res<Achievements>) {
+    if achievements.is_changed() && !achievements.is_added() {
+        high_score::save(FILE_NAME, &achievements.to_text());
+    }
+}
+
+// Watching a replay doesn't count, the run already had its chance
+fn check_achievements(
+    stats: Res<RunStats>,
+    settings: Res<GameSettings>,
+    replay: Res<Replay>,
+    mut achievements: ResMut<Achievements>,
+) {
+    if replay.0.is_some() || !stats.is_changed() {
+        return;
+    }
+    let stage = settings.stage_size;
+    let checks = [
+        (Achievement::TenFood, stats.food >= 10),
+        (Achievement::TopSpeed, stats.top_speed >= settings.max_speed),
+        (Achievement::FiveMinutes, stats.time >= 300.0),
+        (
+            Achievement::QuarterBoard,
+            stats.length as i32 * 4 >= stage.x * stage.y,
+        ),
+    ];
+    for (achievement, reached) in checks {
+        if reached && !achievements.unlocked.contains(&achievement) {
+            achievements.unlock(achievement);
+        }
+    }
+}
+
+fn first_death(replay: Res<Replay>, mut achievements: ResMut<Achievements>) {
+    if replay.0.is_none() && !achievements.unlocked.contains(&Achievement::FirstDeath) {
+        achievements.unlock(Achievement::FirstDeath);
+    }
+}
+
+// Drawn over whatever screen is up every frame and taken off again the next, tiles the
+// screen drew over in the meantime are left alone
+fn toast(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut achievements: ResMut<Achievements>,
+    mut q_term: Query<&mut Terminal>,
+    mut covered: Local<Vec<(IVec2, Tile, char)>>,
+    mut timer: Local<Option<Timer>>,
+) {
+    let mut term = match q_term.get_single_mut() {
+        Ok(term) => term,
+        Err(_) => return,
+    };
+    for (pos, tile, drawn) in covered.drain(..) {
+        if term.is_in_bounds(pos) && term.get_char(pos) == drawn {
+            term.put_tile(pos, tile);
+        }
+    }
+
+    let shown = match achievements.toasts.front() {
+        Some(shown) => *shown,
+        None => return,
+    };
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(TOAST_TIME, false));
+    if timer.tick(time.delta()).finished() {
+        achievements.toasts.pop_front();
+        timer.reset();
+        return;
+    }
+
+    let text = format!(
+        " {} ",
+        loc.format("achievements.toast", &[&shown.name(&loc)])
+    );
+    let width = term.size().x as i32;
+    let y = term.size().y as i32 - 1;
+    let x = (width - locale::width(&text)) / 2;
+    let color = theme.colors().highlight;
+    for (i, c) in text.chars().enumerate() {
+        let pos = IVec2::new(x + i as i32, y);
+        if term.is_in_bounds(pos) {
+            covered.push((pos, *term.get_tile(pos), c));
+            term.put_char(pos, c.fg(color).bg(Color::BLACK));
+        }
+    }
+}
+
+// The title screen's other keys may have changed the state already this frame
+fn open_achievements(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(KeyCode::H) {
+        let _ = state.push(GameState::Achievements);
+    }
+}
+
+fn render_achievements(
+    achievements: Res<Achievements>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let title = loc.get("achievements.title");
+    term.put_string(
+        [locale::centered(&title), 6].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    for (i, achievement) in Achievement::ALL.iter().enumerate() {
+        let y = 3 - 3 * i as i32;
+        let (mark, color) = match achievements.unlocked.contains(achievement) {
+            true => ("[x]", colors.highlight),
+            false => ("[ ]", colors.text),
+        };
+        let name = format!("{} {}", mark, achievement.name(&loc));
+        term.put_string([-14, y].pivot(Pivot::Center), name.fg(color));
+        term.put_string(
+            [-10, y - 1].pivot(Pivot::Center),
+            achievement.description(&loc).fg(colors.faded),
+        );
+    }
+    let count = loc.format(
+        "achievements.unlocked",
+        &[&achievements.unlocked.len(), &Achievement::ALL.len()],
+    );
+    term.put_string(
+        [locale::centered(&count), -12].pivot(Pivot::Center),
+        count.fg(colors.faded),
+    );
+}
+
+fn close_achievements(
+    input: Res<Input<KeyCode>>,
+    controls: Controls,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) || controls.confirm() {
+        state.pop().unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::food::FoodEaten;
+use crate::locale::{self, Localization};
+use crate::render::Theme;
+use crate::snake::{PowerUpTaken, SnakeDied};
+use crate::{GameState, Level, Players, RunStats, Score, Winner};
+
+// Seconds the latest announcement stays on the log line
+const LOG_TIME: f32 = 3.0;
+
+// Text describing something that just happened in the game, for players who can't follow
+// the board. Shown on the log line and printed to stdout on native builds, where a screen
+// reader watching the console picks it up
+pub struct Announcement(pub String);
+
+// The latest announcement and how much longer it's up
+#[derive(Default)]
+pub struct LogLine(Option<(String, Timer)>);
+
+// Nothing is shown or printed unless announcements are turned on in the accessibility
+// settings
+pub struct AnnouncePlugin;
+
+impl Plugin for AnnouncePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Announcement>()
+            .init_resource::<LogLine>()
+            .add_system(publish)
+            .add_system_to_stage(CoreStage::PostUpdate, log_line)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(announce_food.after(crate::food::score_food))
+                    .with_system(announce_power_ups)
+                    .with_system(announce_deaths),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::LevelUp).with_system(announce_level))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(announce_game_over),
+            );
+    }
+}
+
+// Two player games say whose snake it was
+fn for_player(text: String, player: usize, players: Players, loc: &Localization) -> String {
+    match players {
+        Players::One => text,
+        _ => loc.format("announce.player", &[&players.name(player, loc), &text]),
+    }
+}
+
+fn announce_food(
+    mut eaten: EventReader<FoodEaten>,
+    score: Res<Score>,
+    players: Res<Players>,
+    loc: Res<Localization>,
+    mut announce: EventWriter<Announcement>,
+) {
+    for food in eaten.iter() {
+        let text = match (food.spoiled, food.bonus) {
+            (true, _) => loc.get("announce.spoiled"),
+            (false, true) => loc.format("announce.bonus", &[&score.0[food.player]]),
+            (false, false) => loc.format("announce.food", &[&score.0[food.player]]),
+        };
+        let text = for_player(text, food.player, *players, &loc);
+        announce.send(Announcement(text));
+    }
+}
+
+fn announce_power_ups(
+    mut taken: EventReader<PowerUpTaken>,
+    players: Res<Players>,
+    loc: Res<Localization>,
+    mut announce: EventWriter<Announcement>,
+) {
+    for taken in taken.iter() {
+        let name = loc.name("power_up", taken.kind);
+        let text = loc.format("announce.power_up", &[&name]);
+        let text = for_player(text, taken.player, *players, &loc);
+        announce.send(Announcement(text));
+    }
+}
+
+fn announce_deaths(
+    mut died: EventReader<SnakeDied>,
+    players: Res<Players>,
+    loc: Res<Localization>,
+    mut announce: EventWriter<Announcement>,
+) {
+    for died in died.iter() {
+        let text = loc.name("death", died.cause);
+        let text = for_player(text, died.player, *players, &loc);
+        announce.send(Announcement(text));
+    }
+}
+
+fn announce_level(
+    level: Res<Level>,
+    loc: Res<Localization>,
+    mut announce: EventWriter<Announcement>,
+) {
+    announce.send(Announcement(loc.format("announce.level", &[&level.number])));
+}
+
+fn announce_game_over(
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    winner: Res<Winner>,
+    players: Res<Players>,
+    loc: Res<Localization>,
+    mut announce: EventWriter<Announcement>,
+) {
+    let text = match (*players, winner.0) {
+        (Players::One, _) if stats.won => loc.format("announce.won", &[&score.0[0]]),
+        (Players::One, _) => loc.format("announce.game_over", &[&score.0[0]]),
+        (_, Some(winner)) => loc.format("game_over.wins", &[&players.name(winner, &loc)]),
+        (_, None) => loc.get("game_over.draw"),
+    };
+    announce.send(Announcement(text));
+}
+
+// Each one replaces whatever was on the log line
+fn publish(
+    mut announcements: EventReader<Announcement>,
+    accessibility: Res<Accessibility>,
+    mut log: ResMut<LogLine>,
+) {
+    for Announcement(text) in announcements.iter() {
+        if !accessibility.announce {
+            continue;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        println!("{}", text);
+        log.0 = Some((text.clone(), Timer::from_seconds(LOG_TIME, false)));
+    }
+}
+
+// Over the bottom border on every screen, drawn and taken off again the same way as the
+// achievement toasts along the top
+fn log_line(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut log: ResMut<LogLine>,
+    mut q_term: Query<&mut Terminal>,
+    mut covered: Local<Vec<(IVec2, Tile, char)>>,
+) {
+    let mut term = match q_term.get_single_mut() {
+        Ok(term) => term,
+        Err(_) => return,
+    };
+    for (pos, tile, drawn) in covered.drain(..) {
+        if term.is_in_bounds(pos) && term.get_char(pos) == drawn {
+            term.put_tile(pos, tile);
+        }
+    }
+
+    if let Some((_, timer)) = &mut log.0 {
+        if timer.tick(time.delta()).finished() {
+            log.0 = None;
+        }
+    }
+    let text = match &log.0 {
+        Some((text, _)) => text,
+        None => return,
+    };
+    // Cut short to leave the corners alone
+    let width = term.size().x as i32;
+    let text: String = text.chars().take((width - 4).max(0) as usize).collect();
+    let text = format!(" {} ", text);
+    let x = (width - locale::width(&text)) / 2;
+    let color = theme.colors().text;
+    for (i, c) in text.chars().enumerate() {
+        let pos = IVec2::new(x + i as i32, 0);
+        if term.is_in_bounds(pos) {
+            covered.push((pos, *term.get_tile(pos), c));
+            term.put_char(pos, c.fg(color).bg(Color::BLACK));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl, AudioSource};
+
+use crate::food::FoodEaten;
+use crate::snake::{PowerUpTaken, SnakeDied};
+use crate::volume::AudioSettings;
+use crate::{GameSettings, GameState};
+
+// Every food eaten in a row plays the nom this much faster and higher, up to `MAX_PITCH`
+const PITCH_STEP: f64 = 0.04;
+const MAX_PITCH: f64 = 1.6;
+// Panning of sounds at the side walls, 0.5 being the middle and 0 or 1 all the way over
+const PAN_WIDTH: f64 = 0.35;
+
+#[derive(Default)]
+pub struct Sounds {
+    pub nom: Handle<AudioSource>,
+    pub ouch: Handle<AudioSource>,
+    pub ding: Handle<AudioSource>,
+}
+
+// Food each snake has eaten since the game started or it last died
+#[derive(Default)]
+pub struct Streak(pub [usize; 2]);
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sounds>()
+            .init_resource::<Streak>()
+            .add_startup_system(load_sounds)
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_streak))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(play_sounds.after(crate::snake::die)),
+            );
+    }
+}
+
+fn load_sounds(server: Res<AssetServer>, mut sfx: ResMut<Sounds>) {
+    sfx.nom = server.load("nom.wav");
+    sfx.ouch = server.load("ouch.wav");
+    sfx.ding = server.load("ding.wav");
+}
+
+fn reset_streak(mut streak: ResMut<Streak>) {
+    streak.0 = [0; 2];
+}
+
+// Left to right with the cell across the stage
+fn pan(pos: IVec2, settings: &GameSettings) -> f64 {
+    let half = (settings.stage_size.x / 2).max(1) as f64;
+    0.5 + PAN_WIDTH * (pos.x as f64 / half).clamp(-1.0, 1.0)
+}
+
+// One crash sound however many snakes went down, from halfway between them
+fn play_sounds(
+    mut eaten: EventReader<FoodEaten>,
+    mut taken: EventReader<PowerUpTaken>,
+    mut died: EventReader<SnakeDied>,
+    mut streak: ResMut<Streak>,
+    settings: Res<GameSettings>,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    for food in eaten.iter() {
+        // Spoiled food doesn't add to the streak, but doesn't end it either
+        let (sound, pitch) = match food.spoiled {
+            true => (&sfx.ouch, 1.0),
+            false => {
+                let pitch = 1.0 + PITCH_STEP * streak.0[food.player] as f64;
+                streak.0[food.player] += 1;
+                (&sfx.nom, pitch.min(MAX_PITCH))
+            }
+        };
+        audio
+            .play(sound.clone())
+            .with_volume(volume.sfx_gain())
+            .with_playback_rate(pitch)
+            .with_panning(pan(food.pos, &settings));
+    }
+    for power_up in taken.iter() {
+        audio
+            .play(sfx.ding.clone())
+            .with_volume(volume.sfx_gain())
+            .with_panning(pan(power_up.pos, &settings));
+    }
+    let dead: Vec<_> = died.iter().collect();
+    if dead.is_empty() {
+        return;
+    }
+    for died in &dead {
+        streak.0[died.player] = 0;
+    }
+    let pos = dead.iter().map(|died| &died.pos).sum::<IVec2>() / dead.len() as i32;
+    audio
+        .play(sfx.ouch.clone())
+        .with_volume(volume.sfx_gain())
+        .with_panning(pan(pos, &settings));
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use crate::food::POPUP_TIME;
+use crate::locale::Localization;
+use crate::render::Popup;
+use crate::snake::{Body, Player, TickAdvanced};
+use crate::{GameSettings, GameState, RunStats, Score};
+
+// Body segments nearest the head, which are always close by when it turns
+const NECK: usize = 4;
+
+// Food eaten within `combo_ticks` moves of the last one raises the eater's multiplier by
+// one, up to `max_combo`. Every `combo_ticks` moves without food takes one off again. Moves
+// are the first snake's, like everything else counted in ticks
+pub struct Combo {
+    pub multiplier: [usize; 2],
+    // Moves before the multiplier drops
+    pub left: [usize; 2],
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: [1; 2],
+            left: [0; 2],
+        }
+    }
+}
+
+impl Combo {
+    // Food scored by `player` starts or builds the combo, and is worth the multiplier it
+    // leaves behind
+    pub fn eat(&mut self, player: usize, settings: &GameSettings) -> usize {
+        if self.left[player] > 0 {
+            self.multiplier[player] = (self.multiplier[player] + 1).min(settings.max_combo.max(1));
+        }
+        self.left[player] = settings.combo_ticks;
+        self.multiplier[player]
+    }
+
+    // Shown after the score while the combo is running
+    pub fn describe(&self, player: usize, score: usize) -> String {
+        match self.multiplier[player] {
+            1 => score.to_string(),
+            multiplier => format!("{} x{}", score, multiplier),
+        }
+    }
+
+    // Once it's back down to 1 the combo is over, the next food starts a new one
+    pub fn tick(&mut self, settings: &GameSettings) {
+        for (multiplier, left) in self.multiplier.iter_mut().zip(&mut self.left) {
+            *left = left.saturating_sub(1);
+            if *left == 0 && *multiplier > 1 {
+                *multiplier -= 1;
+                if *multiplier > 1 {
+                    *left = settings.combo_ticks;
+                }
+            }
+        }
+    }
+}
+
+pub struct ComboPlugin;
+
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Combo>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_combo))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(
+                        decay_combo
+                            .after(crate::snake::drive)
+                            .before(crate::food::score_food),
+                    )
+                    .with_system(near_misses.after(crate::snake::drive)),
+            );
+    }
+}
+
+fn reset_combo(mut combo: ResMut<Combo>) {
+    *combo = default();
+}
+
+fn decay_combo(
+    mut ticks: EventReader<TickAdvanced>,
+    settings: Res<GameSettings>,
+    mut combo: ResMut<Combo>,
+) {
+    for _ in ticks.iter() {
+        combo.tick(&settings);
+    }
+}
+
+// Points for the head coming within a cell of the snake's own body, diagonals included.
+// Only once each time it comes close, staying alongside doesn't keep scoring, and not for
+// running into it
+fn near_misses(
+    q_snake: Query<(&Body, &Player), Changed<Body>>,
+    settings: Res<GameSettings>,
+    loc: Res<Localization>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    mut commands: Commands,
+    mut near: Local<[bool; 2]>,
+) {
+    for (body, player) in &q_snake {
+        let head = body.0[0];
+        let close = body
+            .0
+            .iter()
+            .skip(NECK)
+            .map(|pos| (*pos - head).abs().max_element())
+            .min()
+            == Some(1);
+        let was_close = std::mem::replace(&mut near[player.0], close);
+        if !close || was_close || settings.near_miss_points == 0 {
+            continue;
+        }
+        score.0[player.0] += settings.near_miss_points;
+        if player.0 == 0 {
+            stats.near_misses += 1;
+        }
+        commands.spawn().insert(Popup {
+            pos: head,
+            text: loc.format("hud.near_miss", &[&settings.near_miss_points]),
+            timer: Timer::from_seconds(POPUP_TIME, false),
+        });
+    }
+}
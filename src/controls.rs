@@ -0,0 +1,338 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::touch::TouchInput;
+
+const STICK_DEADZONE: f32 = 0.5;
+const FILE_NAME: &str = "bindings.txt";
+
+// The second player isn't rebindable, it always has the arrow keys and right shift
+const SECOND_PLAYER_DIRS: [(KeyCode, IVec2); 4] = [
+    (KeyCode::Up, IVec2::Y),
+    (KeyCode::Down, IVec2::NEG_Y),
+    (KeyCode::Left, IVec2::NEG_X),
+    (KeyCode::Right, IVec2::X),
+];
+const SECOND_PLAYER_BOOST: KeyCode = KeyCode::RShift;
+
+// Keys that can be bound, their debug names are what gets saved to disk
+pub const BINDABLE: [KeyCode; 40] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Escape,
+    KeyCode::Back,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Pause,
+    // Held rather than pressed
+    Boost,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Confirm,
+        Action::Pause,
+        Action::Boost,
+    ];
+
+    // Looked up by debug name, as written in the bindings and settings files
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| format!("{:?}", action) == name)
+    }
+}
+
+// Only keys from `BINDABLE` are found
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE
+        .into_iter()
+        .find(|key| format!("{:?}", key) == name)
+}
+
+pub struct KeyBindings(Vec<(Action, KeyCode)>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(vec![
+            (Action::Up, KeyCode::W),
+            (Action::Down, KeyCode::S),
+            (Action::Left, KeyCode::A),
+            (Action::Right, KeyCode::D),
+            (Action::Confirm, KeyCode::Space),
+            (Action::Pause, KeyCode::Escape),
+            (Action::Pause, KeyCode::P),
+            (Action::Boost, KeyCode::LShift),
+        ])
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.0
+            .iter()
+            .filter(move |(bound, _)| *bound == action)
+            .map(|(_, key)| *key)
+    }
+
+    // Replaces every key bound to `action`, refusing a key another action already uses
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        if let Some((taken, _)) = self
+            .0
+            .iter()
+            .find(|(bound, k)| *k == key && *bound != action)
+        {
+            return Err(*taken);
+        }
+        self.0.retain(|(bound, _)| *bound != action);
+        self.0.push((action, key));
+        save(self);
+        Ok(())
+    }
+
+    // Like `rebind` with any number of keys, without checking for conflicts or saving
+    pub fn set(&mut self, action: Action, keys: impl IntoIterator<Item = KeyCode>) {
+        self.0.retain(|(bound, _)| *bound != action);
+        self.0.extend(keys.into_iter().map(|key| (action, key)));
+    }
+
+    // Keys for `action` joined for display, e.g. "Escape/P"
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<_> = self.keys(action).map(|key| format!("{:?}", key)).collect();
+        names.join("/")
+    }
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load());
+    }
+}
+
+// Keyboard and the first connected gamepad, read as one input
+#[derive(SystemParam)]
+pub struct Controls<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    bindings: Res<'w, KeyBindings>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    touch: Res<'w, TouchInput>,
+    // Stick direction from the previous read so holding it only turns once
+    stick: Local<'s, IVec2>,
+}
+
+impl<'w, 's> Controls<'w, 's> {
+    // Direction newly pressed this frame, zero if there was none
+    pub fn dir(&mut self) -> IVec2 {
+        let mut dir = IVec2::ZERO;
+        let gamepad = self.gamepad();
+        let pressed = |action: Action, button: GamepadButtonType| {
+            self.keys.any_just_pressed(self.bindings.keys(action))
+                || gamepad.is_some_and(|gamepad| {
+                    self.buttons
+                        .just_pressed(GamepadButton::new(gamepad, button))
+                })
+        };
+
+        if pressed(Action::Up, GamepadButtonType::DPadUp) {
+            dir.y = 1;
+        }
+
+        if pressed(Action::Down, GamepadButtonType::DPadDown) {
+            dir.y = -1;
+        }
+
+        if pressed(Action::Left, GamepadButtonType::DPadLeft) {
+            dir.x = -1;
+        }
+
+        if pressed(Action::Right, GamepadButtonType::DPadRight) {
+            dir.x = 1;
+        }
+
+        let stick = gamepad.map_or(IVec2::ZERO, |gamepad| self.stick_dir(gamepad));
+        if stick != *self.stick && stick != IVec2::ZERO {
+            dir = stick;
+        }
+        *self.stick = stick;
+
+        if self.touch.dir != IVec2::ZERO {
+            dir = self.touch.dir;
+        }
+
+        dir
+    }
+
+    // The confirm keys, south or start on the gamepad, or a tap
+    pub fn confirm(&self) -> bool {
+        self.touch.tap
+            || self
+                .keys
+                .any_just_pressed(self.bindings.keys(Action::Confirm))
+            || self.gamepad().is_some_and(|gamepad| {
+                self.buttons.any_just_pressed([
+                    GamepadButton::new(gamepad, GamepadButtonType::South),
+                    GamepadButton::new(gamepad, GamepadButtonType::Start),
+                ])
+            })
+    }
+
+    // The pause keys or start on the gamepad
+    pub fn pause(&self) -> bool {
+        self.keys
+            .any_just_pressed(self.bindings.keys(Action::Pause))
+            || self.gamepad().is_some_and(|gamepad| {
+                self.buttons
+                    .just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start))
+            })
+    }
+
+    // Boost keys or the right trigger held down
+    pub fn boost(&self) -> bool {
+        self.keys.any_pressed(self.bindings.keys(Action::Boost))
+            || self.gamepad().is_some_and(|gamepad| {
+                self.buttons.pressed(GamepadButton::new(
+                    gamepad,
+                    GamepadButtonType::RightTrigger2,
+                ))
+            })
+    }
+
+    // Same as `dir` for the second player's keys
+    pub fn second_dir(&self) -> IVec2 {
+        SECOND_PLAYER_DIRS
+            .iter()
+            .filter(|(key, _)| self.keys.just_pressed(*key))
+            .fold(IVec2::ZERO, |dir, (_, d)| dir + *d)
+    }
+
+    pub fn second_boost(&self) -> bool {
+        self.keys.pressed(SECOND_PLAYER_BOOST)
+    }
+
+    // Looked up on every read, so a controller plugged in mid-game is used right away
+    fn gamepad(&self) -> Option<Gamepad> {
+        self.gamepads
+            .iter()
+            .min_by_key(|gamepad| gamepad.id)
+            .copied()
+    }
+
+    // Snaps the left stick to its dominant axis, ignoring drift inside the deadzone
+    fn stick_dir(&self, gamepad: Gamepad) -> IVec2 {
+        let axis = |axis_type| {
+            self.axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+
+        if stick.abs().max_element() < STICK_DEADZONE {
+            IVec2::ZERO
+        } else if stick.x.abs() > stick.y.abs() {
+            IVec2::new(stick.x.signum() as i32, 0)
+        } else {
+            IVec2::new(0, stick.y.signum() as i32)
+        }
+    }
+}
+
+// One "Action Key" pair per line next to the executable, the defaults are used
+// if the file is missing or any line can't be read
+#[cfg(not(target_arch = "wasm32"))]
+fn load() -> KeyBindings {
+    let parse = |line: &str| {
+        let (action, key) = line.split_once(' ')?;
+        Some((Action::from_name(action)?, key_from_name(key.trim())?))
+    };
+    path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| text.lines().map(parse).collect::<Option<Vec<_>>>())
+        .filter(|bindings| {
+            Action::ALL
+                .iter()
+                .all(|a| bindings.iter().any(|(b, _)| b == a))
+        })
+        .map(KeyBindings)
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(bindings: &KeyBindings) {
+    if let Some(path) = path() {
+        let lines: Vec<_> = bindings
+            .0
+            .iter()
+            .map(|(action, key)| format!("{:?} {:?}", action, key))
+            .collect();
+        std::fs::write(path, lines.join("\n")).ok();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path() -> Option<std::path::PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(FILE_NAME))
+}
+
+// No file system on the web, bindings only last for the session
+#[cfg(target_arch = "wasm32")]
+fn load() -> KeyBindings {
+    KeyBindings::default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save(_bindings: &KeyBindings) {}
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::snake::SnakeDied;
+use crate::{GameSettings, GameState, RunStats};
+
+// Brightness left to the board behind the game over screen
+const FADE: f32 = 0.35;
+const MARKER: char = 'X';
+
+// The board as it was drawn when the game ended, kept to go back behind the game over
+// screen whenever it's redrawn
+#[derive(Default)]
+pub struct Wreck {
+    size: UVec2,
+    tiles: Vec<Tile>,
+    // Terminal cells the snakes crashed into, which can be on the border
+    crashes: Vec<IVec2>,
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct CrashPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Wreck>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(clear_wreck))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(record_crash.after(crate::snake::die)),
+            );
+        if !self.headless {
+            app.add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(freeze_board.before(crate::ui::render_game_over)),
+            );
+        }
+    }
+}
+
+fn clear_wreck(mut wreck: ResMut<Wreck>) {
+    *wreck = default();
+}
+
+// Only the first snake's crash goes in the stats, like everything else in them
+fn record_crash(
+    mut died: EventReader<SnakeDied>,
+    settings: Res<GameSettings>,
+    mut stats: ResMut<RunStats>,
+    mut wreck: ResMut<Wreck>,
+) {
+    for died in died.iter() {
+        if died.player == 0 {
+            stats.death = Some(died.cause);
+        }
+        wreck.crashes.push(settings.to_term(died.pos));
+    }
+}
+
+// `render` drew the last move the frame the game ended, before the state changed
+pub(crate) fn freeze_board(q_term: Query<&Terminal>, mut wreck: ResMut<Wreck>) {
+    let term = q_term.single();
+    wreck.size = term.size();
+    wreck.tiles = term.iter().copied().collect();
+}
+
+// Faded so the game over text stands out, with the crashes marked on top. A small stage
+// sits in the middle of the terminal the menus grew it back to, anything else that changed
+// the terminal size since leaves it empty
+pub(crate) fn draw_wreck(term: &mut Terminal, wreck: &Wreck, color: Color) {
+    term.clear();
+    if wreck.size.cmpgt(term.size()).any() {
+        return;
+    }
+    let offset = ((term.size() - wreck.size) / 2).as_ivec2();
+    let width = wreck.size.x.max(1) as usize;
+    let fade = |color: Color| Color::from((Vec4::from(color).truncate() * FADE).extend(1.0));
+    for (i, frozen) in wreck.tiles.iter().enumerate() {
+        let pos = IVec2::new((i % width) as i32, (i / width) as i32) + offset;
+        *term.get_tile_mut(pos) = Tile {
+            fg_color: fade(frozen.fg_color),
+            bg_color: fade(frozen.bg_color),
+            ..*frozen
+        };
+    }
+    for pos in wreck.crashes.iter().map(|pos| *pos + offset) {
+        if term.is_in_bounds(pos) {
+            term.put_char(pos, MARKER.fg(color));
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::high_score::{self, HighScores};
+use crate::replay::Replay;
+use crate::{
+    set_difficulty, Difficulty, GameMode, GameSettings, GameState, Layout, Players, StageSize,
+    WallMode,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+// Daily mode. Everyone playing on the same day, counted in UTC, gets the same seed and
+// layout on the same stage, so the food comes down the same way for all of them. Only the
+// first game each day goes in the daily results
+#[derive(Default)]
+pub struct Daily {
+    // Set from the mode picked on the title screen when each game starts
+    pub enabled: bool,
+    // Days since the unix epoch
+    pub day: u64,
+    // Whether this game is the day's scored attempt
+    pub scored: bool,
+}
+
+impl Daily {
+    pub fn seed(&self) -> u64 {
+        self.day
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::ALL[self.day as usize % Layout::ALL.len()]
+    }
+
+    // The date as YYYY-MM-DD
+    pub fn date(&self) -> String {
+        high_score::day(self.day * DAY)
+    }
+}
+
+pub fn today() -> u64 {
+    high_score::now() / DAY
+}
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Daily>().add_system_set(
+            SystemSet::on_enter(GameState::Playing).with_system(
+                start_daily
+                    .after(crate::keep_picks)
+                    .before(crate::snake::spawn),
+            ),
+        );
+    }
+}
+
+// Single player only, like speedruns. The difficulty, stage size, walls and layout picked
+// on the title screen are swapped for the ones the daily game is played on, the way
+// replays do with theirs, and come back with the title screen
+fn start_daily(
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    replay: Res<Replay>,
+    high_scores: Res<HighScores>,
+    mut daily: ResMut<Daily>,
+    mut difficulty: ResMut<Difficulty>,
+    mut stage_size: ResMut<StageSize>,
+    mut settings: ResMut<GameSettings>,
+    mut wall_mode: ResMut<WallMode>,
+    mut layout: ResMut<Layout>,
+) {
+    daily.enabled = *mode == GameMode::Daily && *players == Players::One && replay.0.is_none();
+    if !daily.enabled {
+        return;
+    }
+    daily.day = today();
+    daily.scored = !high_scores
+        .daily
+        .iter()
+        .any(|run| run.date / DAY == daily.day);
+    set_difficulty(&mut difficulty, &mut settings, Difficulty::Normal);
+    settings.stage_size += StageSize::Normal.extra_space() - stage_size.extra_space();
+    *stage_size = StageSize::Normal;
+    *wall_mode = WallMode::Solid;
+    *layout = daily.layout();
+}
@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controls::Controls;
+use crate::locale::{self, Localization};
+use crate::render::{fit_menu, Theme};
+use crate::ui::draw_row;
+use crate::{GameSettings, GameState};
+
+// Saved levels shown on the level select screen at once, it scrolls past that
+const ROWS_SHOWN: usize = 8;
+// The cursor moves on the arrow keys whatever the bindings are, the letters place things
+const CURSOR_KEYS: [(KeyCode, IVec2); 4] = [
+    (KeyCode::Up, IVec2::Y),
+    (KeyCode::Down, IVec2::NEG_Y),
+    (KeyCode::Left, IVec2::NEG_X),
+    (KeyCode::Right, IVec2::X),
+];
+
+// A layout made in the editor, saved as `assets/levels/<name>.toml` in stage cells, e.g.
+// `walls = [[3, 4], [3, 5]]`. Anything outside the stage it's played on is left out
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LevelFile {
+    pub walls: Vec<[i32; 2]>,
+    // Both ends of each pair, every portal leads to the other one
+    pub portals: Vec<[[i32; 2]; 2]>,
+    // Where each player's snake starts, the middle for any that aren't set
+    pub spawns: Vec<[i32; 2]>,
+}
+
+impl LevelFile {
+    pub fn spawn(&self, player: usize) -> Option<IVec2> {
+        self.spawns.get(player).copied().map(IVec2::from)
+    }
+
+    // One thing to a cell, whatever was there goes
+    fn clear(&mut self, pos: IVec2) {
+        let cell = pos.to_array();
+        self.walls.retain(|wall| *wall != cell);
+        self.portals.retain(|pair| !pair.contains(&cell));
+        self.spawns.retain(|spawn| *spawn != cell);
+    }
+}
+
+// The level picked on the level select screen and its name, played in place of the layout
+// until another layout is picked on the title screen
+#[derive(Default)]
+pub struct CustomLevel(pub Option<(String, LevelFile)>);
+
+impl CustomLevel {
+    pub fn level(&self) -> Option<&LevelFile> {
+        self.0.as_ref().map(|(_, level)| level)
+    }
+}
+
+// Names of the saved levels, read again whenever the level select screen comes up
+#[derive(Default)]
+struct LevelList(Vec<String>);
+
+// The level being edited and the cell the cursor is on
+#[derive(Default)]
+struct Editor {
+    name: String,
+    level: LevelFile,
+    cursor: IVec2,
+    // First end of a portal pair that's waiting for its other end
+    portal: Option<IVec2>,
+}
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CustomLevel>()
+            .init_resource::<LevelList>()
+            .init_resource::<Editor>()
+            .add_system_set(SystemSet::on_update(GameState::Title).with_system(open_levels))
+            .add_system_set(SystemSet::on_enter(GameState::Levels).with_system(list_levels))
+            .add_system_set(
+                SystemSet::on_resume(GameState::Levels)
+                    .with_system(list_levels)
+                    .with_system(fit_menu),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Levels).with_system(level_menu))
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(fit_editor))
+            .add_system_set(SystemSet::on_update(GameState::Editor).with_system(edit));
+    }
+}
+
+// The title screen's other keys may have changed the state already this frame
+fn open_levels(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(KeyCode::E) {
+        let _ = state.push(GameState::Levels);
+    }
+}
+
+fn list_levels(mut list: ResMut<LevelList>) {
+    list.0 = list_files();
+}
+
+// A new level on the first row and the saved ones below it. Confirm plays the selected
+// level and E opens it in the editor
+fn level_menu(
+    mut controls: Controls,
+    input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    mut selected: Local<usize>,
+    list: Res<LevelList>,
+    mut custom: ResMut<CustomLevel>,
+    mut editor: ResMut<Editor>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        state.pop().unwrap();
+        return;
+    }
+
+    let dir = controls.dir() + controls.second_dir();
+    let len = list.0.len() as i32 + 1;
+    *selected = (*selected as i32 - dir.y).rem_euclid(len) as usize;
+    let open = input.just_pressed(KeyCode::E) || (*selected == 0 && controls.confirm());
+    if open || controls.confirm() {
+        let picked = match *selected {
+            0 => Some((new_name(&list.0), LevelFile::default())),
+            i => load(&list.0[i - 1]).map(|level| (list.0[i - 1].clone(), level)),
+        };
+        if let Some((name, level)) = picked {
+            match open {
+                true => {
+                    *editor = Editor {
+                        name,
+                        level,
+                        ..default()
+                    };
+                    let _ = state.push(GameState::Editor);
+                }
+                false => {
+                    custom.0 = Some((name, level));
+                    state.pop().unwrap();
+                }
+            }
+            return;
+        }
+    }
+
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let title = loc.get("levels.title");
+    term.put_string(
+        [locale::centered(&title), 6].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    let new = loc.get("levels.new");
+    let rows = std::iter::once(&new).chain(&list.0).enumerate();
+    let first = (*selected + 1).saturating_sub(ROWS_SHOWN);
+    for (y, (i, name)) in (0..).zip(rows.skip(first).take(ROWS_SHOWN)) {
+        draw_row(&mut term, 3 - y, name, i == *selected, colors);
+    }
+    for (y, key) in [(-7, "levels.help"), (-8, "common.back")] {
+        let text = loc.get(key);
+        term.put_string(
+            [locale::centered(&text), y].pivot(Pivot::Center),
+            text.fg(colors.faded),
+        );
+    }
+}
+
+// The level is drawn on a stage the size of the one the next game is played on
+fn fit_editor(settings: Res<GameSettings>, mut q_term: Query<&mut Terminal>) {
+    q_term
+        .single_mut()
+        .resize((settings.stage_size + 2).as_uvec2());
+}
+
+fn edit(
+    input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    mut editor: ResMut<Editor>,
+    mut custom: ResMut<CustomLevel>,
+    settings: Res<GameSettings>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        state.pop().unwrap();
+        return;
+    }
+    // Saving picks the level to play as well, which is the only way to play it on the web
+    if input.just_pressed(KeyCode::Return) {
+        save(&editor.name, &editor.level);
+        custom.0 = Some((editor.name.clone(), editor.level.clone()));
+        state.pop().unwrap();
+        return;
+    }
+
+    let editor = &mut *editor;
+    let dir = CURSOR_KEYS
+        .iter()
+        .filter(|(key, _)| input.just_pressed(*key))
+        .fold(IVec2::ZERO, |dir, (_, d)| dir + *d);
+    if settings.in_bounds(editor.cursor + dir) {
+        editor.cursor += dir;
+    }
+    let cursor = editor.cursor;
+    let cell = cursor.to_array();
+    let level = &mut editor.level;
+    if input.just_pressed(KeyCode::W) {
+        let wall = level.walls.contains(&cell);
+        level.clear(cursor);
+        if !wall {
+            level.walls.push(cell);
+        }
+    }
+    // The first press marks one end and the second one the other, pressing on a portal
+    // takes its pair away
+    if input.just_pressed(KeyCode::P) {
+        let portal = level.portals.iter().any(|pair| pair.contains(&cell));
+        level.clear(cursor);
+        editor.portal = match (portal, editor.portal) {
+            (true, _) => None,
+            (false, Some(start)) if start != cursor => {
+                level.clear(start);
+                level.portals.push([start.to_array(), cell]);
+                None
+            }
+            (false, _) => Some(cursor),
+        };
+    }
+    // Spawn points go to the players in the order they're placed, a third one replaces the
+    // first
+    if input.just_pressed(KeyCode::S) {
+        let spawn = level.spawns.contains(&cell);
+        level.clear(cursor);
+        if !spawn {
+            if level.spawns.len() == 2 {
+                level.spawns.remove(0);
+            }
+            level.spawns.push(cell);
+        }
+    }
+    if input.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        level.clear(cursor);
+        if editor.portal == Some(cursor) {
+            editor.portal = None;
+        }
+    }
+
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    for wall in &level.walls {
+        let pos = settings.to_term(IVec2::from(*wall));
+        term.put_char(pos, settings.wall_glyph.fg(colors.wall));
+    }
+    for pos in level.portals.iter().flatten() {
+        let pos = settings.to_term(IVec2::from(*pos));
+        term.put_char(pos, settings.portal_glyph.fg(colors.title));
+    }
+    if let Some(pos) = editor.portal {
+        term.put_char(
+            settings.to_term(pos),
+            settings.portal_glyph.fg(colors.faded),
+        );
+    }
+    for (i, spawn) in level.spawns.iter().enumerate() {
+        let pos = settings.to_term(IVec2::from(*spawn));
+        let digit = char::from_digit(i as u32 + 1, 10).unwrap();
+        term.put_char(pos, digit.fg(colors.highlight));
+    }
+    term.get_tile_mut(settings.to_term(cursor)).bg_color = colors.faded;
+
+    // Along the borders, cut short on small stages
+    let width = term.size().x as usize - 2;
+    let title = format!(" {} ", loc.format("editor.title", &[&editor.name]));
+    let keys = format!(" {} ", loc.get("editor.keys"));
+    let top = term.size().y as i32 - 1;
+    for (y, text) in [(top, title), (0, keys)] {
+        let text: String = text.chars().take(width).collect();
+        let x = (term.size().x as i32 - locale::width(&text)) / 2;
+        term.put_string([x, y], text.fg(colors.text));
+    }
+}
+
+// `level_1`, `level_2` and so on, the first one that isn't taken
+fn new_name(names: &[String]) -> String {
+    (1..)
+        .map(|i| format!("level_{}", i))
+        .find(|name| !names.contains(name))
+        .unwrap()
+}
+
+// Where Bevy's asset server looks, the crate root when run through cargo and next to the
+// executable otherwise. Unreadable files are left off the list
+#[cfg(not(target_arch = "wasm32"))]
+fn dir() -> Option<std::path::PathBuf> {
+    let root = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(root) => std::path::PathBuf::from(root),
+        None => std::env::current_exe().ok()?.parent()?.to_path_buf(),
+    };
+    Some(root.join("assets").join("levels"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_files() -> Vec<String> {
+    let entries = dir().and_then(|dir| std::fs::read_dir(dir).ok());
+    let mut names: Vec<_> = entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            match path.extension()? == "toml" {
+                true => Some(path.file_stem()?.to_str()?.to_string()),
+                false => None,
+            }
+        })
+        .filter(|name| load(name).is_some())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load(name: &str) -> Option<LevelFile> {
+    let text = std::fs::read_to_string(dir()?.join(format!("{}.toml", name))).ok()?;
+    toml::from_str(&text).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(name: &str, level: &LevelFile) {
+    if let (Some(dir), Ok(text)) = (dir(), toml::to_string(level)) {
+        std::fs::create_dir_all(&dir).ok();
+        std::fs::write(dir.join(format!("{}.toml", name)), text).ok();
+    }
+}
+
+// No file system on the web, a level made in the editor can be played until another
+// layout is picked but isn't kept
+#[cfg(target_arch = "wasm32")]
+fn list_files() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load(_name: &str) -> Option<LevelFile> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save(_name: &str, _level: &LevelFile) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_files_round_trip() {
+        let level = LevelFile {
+            walls: vec![[3, 4], [5, 5], [100, 0]],
+            portals: vec![[[-6, 2], [6, -2]]],
+            spawns: vec![[5, 5]],
+        };
+        let text = toml::to_string(&level).unwrap();
+        assert_eq!(toml::from_str::<LevelFile>(&text).unwrap(), level);
+        // Keys that are left out are empty
+        let walls: LevelFile = toml::from_str("walls = [[1, 2]]").unwrap();
+        assert_eq!((walls.walls.len(), walls.spawns.len()), (1, 0));
+    }
+
+    #[test]
+    fn placing_clears_the_cell() {
+        let mut level = LevelFile {
+            walls: vec![[3, 4], [5, 5]],
+            portals: vec![[[5, 5], [6, -2]]],
+            spawns: vec![[5, 5], [0, 0]],
+        };
+        level.clear(IVec2::new(5, 5));
+        assert_eq!(level.walls, [[3, 4]]);
+        assert!(level.portals.is_empty());
+        assert_eq!(level.spawn(0), Some(IVec2::ZERO));
+    }
+}
@@ -0,0 +1,427 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::combo::Combo;
+use crate::locale::Localization;
+use crate::render::Popup;
+use crate::snake::{is_risky, Body, Critter, Effects, GridPos, Grow, Player, Portal, Shrink, Wall};
+use crate::{
+    victory, GameRng, GameSettings, GameState, RunStats, Score, ShrinkingBounds, WallMode,
+};
+
+pub(crate) const POPUP_TIME: f32 = 0.75;
+
+#[derive(Component)]
+pub struct Food {
+    pub pos: IVec2,
+    pub value: usize,
+    // Ticks left before bonus or poison food disappears
+    pub ttl: Option<usize>,
+    // Poison shrinks the snake instead of growing it
+    pub poison: bool,
+}
+
+// Moves left before regular food goes rotten, and then before the rotten food is gone
+#[derive(Component)]
+pub struct FoodTimer(pub usize);
+
+// Eating rotten food costs a segment instead of growing the snake
+#[derive(Component)]
+pub struct Rotten;
+
+// Picked up like food, `apply_effects` gives the snake the effect instead of growing it
+#[derive(Component)]
+pub struct PowerUp {
+    pub pos: IVec2,
+    pub kind: PowerUpKind,
+    // Ticks left before it disappears
+    pub ttl: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    SlowDown,
+    // Drops `power_up_shrink` tail segments right away, the rest last `power_up_ticks` moves
+    Shrink,
+    // The snake can pass through its own body
+    Ghost,
+    DoublePoints,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::SlowDown,
+        PowerUpKind::Shrink,
+        PowerUpKind::Ghost,
+        PowerUpKind::DoublePoints,
+    ];
+
+    pub fn glyph(self) -> char {
+        match self {
+            PowerUpKind::SlowDown => '≈',
+            PowerUpKind::Shrink => '↓',
+            PowerUpKind::Ghost => 'Ω',
+            PowerUpKind::DoublePoints => '$',
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::SlowDown => Color::CYAN,
+            PowerUpKind::Shrink => Color::PURPLE,
+            PowerUpKind::Ghost => Color::SILVER,
+            PowerUpKind::DoublePoints => Color::GOLD,
+        }
+    }
+}
+
+// Food eaten by each player
+#[derive(Default)]
+pub struct FoodCount(pub [usize; 2]);
+
+// How many foods should be on the board at once, poison not included
+pub struct FoodTarget(pub usize);
+
+impl Default for FoodTarget {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+// Sent by `eat` for every piece of food a snake runs into
+pub struct FoodEaten {
+    pub player: usize,
+    pub pos: IVec2,
+    pub value: usize,
+    pub bonus: bool,
+    // Poison or rotten, the snake shrinks instead and no points are scored
+    pub spoiled: bool,
+    // Eaten with a wall or the body right next to the head
+    pub risky: bool,
+}
+
+// Extra points for eating while a wall or the body is right next to the head
+pub struct RiskBonus {
+    pub points: usize,
+    pub grow: bool,
+}
+
+impl Default for RiskBonus {
+    fn default() -> Self {
+        Self {
+            points: 2,
+            grow: false,
+        }
+    }
+}
+
+pub struct FoodPlugin;
+
+impl Plugin for FoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FoodEaten>().add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(eat.after(crate::snake::drive))
+                .with_system(score_food.after(eat))
+                .with_system(expire_food.after(eat))
+                .with_system(spoil_food.after(eat).after(crate::snake::roam_critters))
+                // Spawned food only exists once commands are applied, so place it
+                // after the snake has moved or it could land under the head unseen
+                .with_system(
+                    make_food
+                        .after(crate::snake::shrink)
+                        .after(expire_food)
+                        .after(spoil_food)
+                        .after(crate::snake::spawn_critters),
+                ),
+        );
+    }
+}
+
+pub(crate) fn make_food(
+    mut commands: Commands,
+    q_food: Query<&Food>,
+    q_body: Query<&Body>,
+    q_grow: Query<&Grow>,
+    q_wall: Query<&Wall>,
+    q_portal: Query<&Portal>,
+    q_critter: Query<&Critter>,
+    q_power_up: Query<&PowerUp>,
+    q_rotten: Query<(), With<Rotten>>,
+    target: Res<FoodTarget>,
+    settings: Res<GameSettings>,
+    bounds: Res<ShrinkingBounds>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = &mut rng.0;
+    // Leftover poison and rotten food don't count, there should always be something to eat
+    let fresh = q_food.iter().filter(|food| !food.poison).count() - q_rotten.iter().count();
+    let missing = target.0.saturating_sub(fresh);
+    if missing > 0 && !q_body.is_empty() {
+        let body: Vec<_> = q_body
+            .iter()
+            .flat_map(|body| body.0.iter().copied())
+            .collect();
+        // Nothing more once a snake has covered enough of the stage to win
+        let blocked = victory::blocked_cells(&q_wall, &q_portal);
+        let target = victory::fill_target(&settings, &bounds, &blocked);
+        if q_body.iter().any(|body| victory::covered(body) >= target) {
+            return;
+        }
+        // Pending growth will be appended at these cells, treat them as body
+        let free: Vec<_> = settings
+            .stage_cells()
+            .filter(|pos| bounds.is_safe(*pos, &settings))
+            .filter(|pos| !body.contains(pos) && !q_grow.iter().any(|grow| grow.pos == *pos))
+            .filter(|pos| !q_wall.iter().any(|wall| wall.pos == *pos))
+            .filter(|pos| !q_portal.iter().any(|portal| portal.pos == *pos))
+            .filter(|pos| !q_critter.iter().any(|critter| critter.pos == *pos))
+            .filter(|pos| !q_food.iter().any(|food| food.pos == *pos))
+            .filter(|pos| !q_power_up.iter().any(|power_up| power_up.pos == *pos))
+            .collect();
+        // Food takes the first cells, each one may bring a poison and a power-up along
+        // on the rest
+        let cells: Vec<_> = free.choose_multiple(rng, missing * 3).collect();
+        let (food_cells, rest) = cells.split_at(missing.min(cells.len()));
+        let (poison_cells, power_up_cells) = rest.split_at(missing.min(rest.len()));
+
+        // Nothing is spawned once the snakes fill the whole board
+        for &&pos in food_cells {
+            let food = if rng.gen_bool(settings.bonus_chance) {
+                Food {
+                    pos,
+                    value: settings.bonus_value,
+                    ttl: Some(settings.bonus_ticks),
+                    poison: false,
+                }
+            } else {
+                Food {
+                    pos,
+                    value: 1,
+                    ttl: None,
+                    poison: false,
+                }
+            };
+            let spoils = food.ttl.is_none() && settings.spoil_ticks > 0;
+            let mut entity = commands.spawn();
+            entity.insert(food);
+            if spoils {
+                entity.insert(FoodTimer(settings.spoil_ticks));
+            }
+        }
+        for &&pos in poison_cells {
+            if rng.gen_bool(settings.poison_chance) {
+                commands.spawn().insert(Food {
+                    pos,
+                    value: 0,
+                    ttl: Some(settings.bonus_ticks),
+                    poison: true,
+                });
+            }
+        }
+        for &&pos in power_up_cells {
+            if rng.gen_bool(settings.power_up_chance) {
+                commands.spawn().insert(PowerUp {
+                    pos,
+                    kind: *PowerUpKind::ALL.choose(rng).unwrap(),
+                    ttl: settings.bonus_ticks,
+                });
+            }
+        }
+    }
+}
+
+fn expire_food(
+    mut commands: Commands,
+    mut q_food: Query<(Entity, &mut Food)>,
+    mut q_power_up: Query<(Entity, &mut PowerUp)>,
+    q_snake: Query<(), Changed<GridPos>>,
+) {
+    if q_snake.is_empty() {
+        return;
+    }
+
+    for (entity, mut food) in &mut q_food {
+        if let Some(ttl) = &mut food.ttl {
+            *ttl = ttl.saturating_sub(1);
+            if *ttl == 0 {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+    for (entity, mut power_up) in &mut q_power_up {
+        power_up.ttl = power_up.ttl.saturating_sub(1);
+        if power_up.ttl == 0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Ticks on every move like `expire_food`. Food a head or a critter got to this move is
+// already on its way out, and can't be made rotten
+fn spoil_food(
+    mut commands: Commands,
+    mut q_food: Query<(Entity, &Food, &mut FoodTimer, Option<&Rotten>)>,
+    q_snake: Query<&GridPos, Changed<GridPos>>,
+    q_critter: Query<&Critter>,
+    settings: Res<GameSettings>,
+) {
+    if q_snake.is_empty() {
+        return;
+    }
+
+    let taken: Vec<_> = q_snake
+        .iter()
+        .map(|pos| pos.0)
+        .chain(q_critter.iter().map(|critter| critter.pos))
+        .collect();
+    for (entity, food, mut timer, rotten) in &mut q_food {
+        if taken.contains(&food.pos) {
+            continue;
+        }
+        timer.0 = timer.0.saturating_sub(1);
+        match (timer.0, rotten) {
+            (0, Some(_)) => commands.entity(entity).despawn(),
+            (0, None) => {
+                timer.0 = settings.rot_ticks;
+                commands.entity(entity).insert(Rotten);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Eating only changes the snake, scoring, sounds and effects are left to whoever reads
+// `FoodEaten`
+pub(crate) fn eat(
+    q_food: Query<(Entity, &Food, Option<&Rotten>)>,
+    q_wall: Query<&Wall>,
+    q_snake: Query<(&Body, &GridPos, &Player), Changed<GridPos>>,
+    mut commands: Commands,
+    mut count: ResMut<FoodCount>,
+    mut eaten: EventWriter<FoodEaten>,
+    risk: Res<RiskBonus>,
+    settings: Res<GameSettings>,
+    wall_mode: Res<WallMode>,
+) {
+    let walls: Vec<_> = q_wall.iter().map(|wall| wall.pos).collect();
+    for (body, pos, player) in &q_snake {
+        let count = &mut count.0[player.0];
+        for (e_food, food, rotten) in &q_food {
+            if pos.0 != food.pos {
+                continue;
+            }
+            commands.entity(e_food).despawn();
+            let spoiled = food.poison || rotten.is_some();
+            let risky = !spoiled && is_risky(&body.0, &walls, &settings, *wall_mode);
+            eaten.send(FoodEaten {
+                player: player.0,
+                pos: food.pos,
+                value: food.value,
+                bonus: food.ttl.is_some(),
+                spoiled,
+                risky,
+            });
+            if spoiled {
+                let segments = match rotten {
+                    Some(_) => 1,
+                    None => settings.poison_shrink,
+                };
+                commands.spawn().insert(Shrink {
+                    segments,
+                    player: player.0,
+                });
+                continue;
+            }
+
+            *count += food.value;
+            let tail = *body.0.back().unwrap();
+            commands.spawn().insert(Grow {
+                turns: *count,
+                pos: tail,
+                player: player.0,
+            });
+            if risky && risk.grow {
+                commands.spawn().insert(Grow {
+                    turns: risk.points.min(*count),
+                    pos: tail,
+                    player: player.0,
+                });
+            }
+        }
+    }
+}
+
+pub(crate) fn score_food(
+    mut eaten: EventReader<FoodEaten>,
+    q_snake: Query<(&Player, &Effects)>,
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
+    risk: Res<RiskBonus>,
+    settings: Res<GameSettings>,
+    loc: Res<Localization>,
+) {
+    for food in eaten.iter().filter(|food| !food.spoiled) {
+        let multiplier = combo.eat(food.player, &settings);
+        let doubled = q_snake
+            .iter()
+            .any(|(p, effects)| p.0 == food.player && effects.has(PowerUpKind::DoublePoints));
+        let points = match doubled {
+            true => 2,
+            false => 1,
+        };
+        let score = &mut score.0[food.player];
+        *score += food.value * points * multiplier;
+        if food.player == 0 {
+            stats.food += 1;
+            stats.best_combo = stats.best_combo.max(multiplier);
+        }
+        if food.risky {
+            let bonus = risk.points * points;
+            *score += bonus;
+            commands.spawn().insert(Popup {
+                pos: food.pos,
+                text: loc.format("hud.risk", &[&bonus]),
+                timer: Timer::from_seconds(POPUP_TIME, false),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn risk_popup_shows_the_doubled_bonus() {
+        let mut world = World::new();
+        world.init_resource::<Events<FoodEaten>>();
+        world.init_resource::<Score>();
+        world.init_resource::<RunStats>();
+        world.init_resource::<Combo>();
+        world.init_resource::<RiskBonus>();
+        world.init_resource::<GameSettings>();
+        world.init_resource::<Localization>();
+        world
+            .spawn()
+            .insert(Player(0))
+            .insert(Effects(vec![(PowerUpKind::DoublePoints, 10)]));
+        world.send_event(FoodEaten {
+            player: 0,
+            pos: IVec2::ZERO,
+            value: 1,
+            bonus: false,
+            spoiled: false,
+            risky: true,
+        });
+        SystemStage::single(score_food).run(&mut world);
+
+        let bonus = world.resource::<RiskBonus>().points * 2;
+        assert_eq!(world.resource::<Score>().0[0], 2 + bonus);
+        let mut q_popup = world.query::<&Popup>();
+        assert_eq!(q_popup.single(&world).text, format!("+risk {}", bonus));
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_kira_audio::Audio;
+
+use crate::accessibility::Accessibility;
+use crate::audio::Sounds;
+use crate::combo::ComboPlugin;
+use crate::controls::KeyBindings;
+use crate::crash::CrashPlugin;
+use crate::daily::DailyPlugin;
+use crate::editor::CustomLevel;
+use crate::food::{FoodCount, FoodPlugin, FoodTarget, RiskBonus};
+use crate::high_score::HighScores;
+use crate::input::InputPlugin;
+use crate::locale::Localization;
+use crate::practice::PracticePlugin;
+use crate::render::Theme;
+use crate::replay::{Recording, Replay};
+use crate::snake::{spawn, SnakePlugin};
+use crate::speedrun::SpeedrunPlugin;
+use crate::touch::TouchInput;
+use crate::ui::UiPlugin;
+use crate::victory::VictoryPlugin;
+use crate::volume::AudioSettings;
+use crate::zen::ZenPlugin;
+use crate::{
+    keep_picks, AutoRestart, Countdown, Difficulty, GameMode, GameRng, GameSettings, GameState,
+    Layout, Level, Picks, Players, RunStats, Score, ShrinkingBounds, StageSize, WallMode, Winner,
+};
+
+// The rules of the game on their own: nothing is drawn or played and the clock only moves
+// when `tick` says so. The systems are the ones the game runs, from the same plugins with
+// everything to do with the window, terminal, audio and saved files left out
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let mut time = Time::default();
+        time.update_with_instant(time.startup());
+
+        app.insert_resource(time)
+            .insert_resource(Audio::default())
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<TouchInput>()
+            .init_resource::<Sounds>()
+            .init_resource::<Theme>()
+            .init_resource::<Localization>()
+            .init_resource::<Accessibility>()
+            .init_resource::<FoodCount>()
+            .init_resource::<FoodTarget>()
+            .init_resource::<Score>()
+            .init_resource::<RunStats>()
+            .init_resource::<Countdown>()
+            .init_resource::<RiskBonus>()
+            .init_resource::<WallMode>()
+            .init_resource::<GameSettings>()
+            .init_resource::<HighScores>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<Layout>()
+            .init_resource::<Players>()
+            .init_resource::<Difficulty>()
+            .init_resource::<GameMode>()
+            .init_resource::<Winner>()
+            .init_resource::<Level>()
+            .init_resource::<ShrinkingBounds>()
+            .init_resource::<AudioSettings>()
+            .insert_resource(AutoRestart(None))
+            .init_resource::<GameRng>()
+            .init_resource::<Recording>()
+            .init_resource::<StageSize>()
+            .init_resource::<Replay>()
+            .init_resource::<CustomLevel>()
+            .init_resource::<Picks>()
+            .add_state(GameState::Playing)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(keep_picks.before(spawn)),
+            )
+            .add_plugin(InputPlugin { headless: true })
+            .add_plugin(SnakePlugin)
+            .add_plugin(FoodPlugin)
+            .add_plugin(ComboPlugin)
+            .add_plugin(CrashPlugin { headless: true })
+            .add_plugin(VictoryPlugin { headless: true })
+            .add_plugin(PracticePlugin { headless: true })
+            .add_plugin(SpeedrunPlugin { headless: true })
+            .add_plugin(DailyPlugin)
+            .add_plugin(ZenPlugin { headless: true })
+            .add_plugin(UiPlugin { headless: true });
+    }
+}
+
+// Runs a single frame lasting `delta` with `keys` freshly pressed
+pub fn tick(app: &mut App, keys: &[KeyCode], delta: Duration) {
+    let mut input = app.world.resource_mut::<Input<KeyCode>>();
+    input.release_all();
+    input.clear();
+    for key in keys {
+        input.press(*key);
+    }
+
+    let mut time = app.world.resource_mut::<Time>();
+    let next = time.last_update().unwrap() + delta;
+    time.update_with_instant(next);
+
+    app.update();
+}
+
+// Presses `key` and advances the clock far enough for the snake to move exactly one cell
+pub fn step(app: &mut App, key: KeyCode) {
+    tick(app, &[key], Duration::from_secs(1));
+}
+
+// What the tests play on: a single snake and its food on an empty stage, moving right away
+#[cfg(test)]
+pub(crate) fn headless_app() -> App {
+    let mut app = crate::build_game_app(true);
+    app.insert_resource(GameSettings {
+        wall_count: 0,
+        poison_chance: 0.,
+        power_up_chance: 0.,
+        spoil_ticks: 0,
+        countdown: 0.,
+        // Each level puts the snake back at the start, only the test for them goes past
+        // the first
+        level_food: 0,
+        ..default()
+    });
+    app
+}
@@ -0,0 +1,373 @@
+use bevy::prelude::{App, IVec2, Plugin, Res};
+
+use crate::snake::DeathCause;
+use crate::Difficulty;
+
+const MAX_ENTRIES: usize = 10;
+const HIGH_SCORES: &str = "high_scores";
+// Daily results and zen runs go in the same file, on lines of their own that start with
+// these
+const DAILY: &str = "daily";
+const ZEN: &str = "zen";
+const TOTALS: &str = "totals";
+const GHOST: &str = "ghost";
+const FIRST_LEVEL: usize = 1;
+
+#[derive(Clone)]
+pub struct Run {
+    pub score: usize,
+    pub length: usize,
+    // Seconds survived
+    pub time: u32,
+    // Seconds since the unix epoch when the run ended
+    pub date: u64,
+    // Difficulty preset it was played on, saved by its index
+    pub difficulty: Difficulty,
+    // Highest level reached
+    pub level: usize,
+    // Typed in after the run, empty until then
+    pub name: String,
+    // Filled the stage
+    pub won: bool,
+    // What the snake crashed into, `None` for runs that didn't end in a crash
+    pub death: Option<DeathCause>,
+}
+
+impl Run {
+    // An empty name or no crash is written as "-" so the fields after it stay in place
+    fn to_line(&self) -> String {
+        let name = match self.name.is_empty() {
+            true => "-",
+            false => &self.name,
+        };
+        let death = self
+            .death
+            .map_or_else(|| "-".to_string(), |death| format!("{:?}", death));
+        format!(
+            "{} {} {} {} {} {} {} {} {}",
+            self.score,
+            self.length,
+            self.time,
+            self.date,
+            self.difficulty.index(),
+            self.level,
+            name,
+            u8::from(self.won),
+            death
+        )
+    }
+
+    // Runs saved before difficulty or levels were recorded count as Normal and the
+    // first level, names are left empty, wins weren't possible and the crash is unknown
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        Some(Run {
+            score: fields.next()?.parse().ok()?,
+            length: fields.next()?.parse().ok()?,
+            time: fields.next()?.parse().ok()?,
+            date: fields.next()?.parse().ok()?,
+            difficulty: match fields.next() {
+                Some(d) => *Difficulty::ALL.get(d.parse::<usize>().ok()?)?,
+                None => Difficulty::Normal,
+            },
+            level: fields
+                .next()
+                .map_or(Some(FIRST_LEVEL), |l| l.parse().ok())?,
+            name: fields
+                .next()
+                .filter(|name| *name != "-")
+                .unwrap_or_default()
+                .to_string(),
+            won: fields.next() == Some("1"),
+            death: fields.next().and_then(|name| {
+                DeathCause::ALL
+                    .into_iter()
+                    .find(|death| format!("{:?}", death) == name)
+            }),
+        })
+    }
+
+    // Day the run ended as YYYY-MM-DD in UTC
+    pub fn day(&self) -> String {
+        day(self.date)
+    }
+}
+
+// Day of a time in seconds since the unix epoch as YYYY-MM-DD in UTC
+pub fn day(date: u64) -> String {
+    // Days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (date / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+// The best runs so far, highest score first
+#[derive(Default)]
+pub struct HighScores {
+    pub runs: Vec<Run>,
+    // Place of the last submitted run if it made the table
+    pub latest: Option<usize>,
+    // The scored attempt of each of the latest daily games, newest first
+    pub daily: Vec<Run>,
+    // The best zen runs, which don't compete with the others
+    pub zen: Vec<Run>,
+}
+
+// Ties go below the runs that got there first. Returns the place if the run made the table
+fn insert(runs: &mut Vec<Run>, run: Run) -> Option<usize> {
+    let place = runs.partition_point(|other| other.score >= run.score);
+    if place >= MAX_ENTRIES {
+        return None;
+    }
+    runs.insert(place, run);
+    runs.truncate(MAX_ENTRIES);
+    Some(place)
+}
+
+impl HighScores {
+    pub fn best(&self) -> usize {
+        self.runs.first().map_or(0, |run| run.score)
+    }
+
+    pub fn submit(&mut self, run: Run) {
+        self.latest = insert(&mut self.runs, run);
+    }
+
+    // Returns the place it made in the zen table
+    pub fn submit_zen(&mut self, run: Run) -> Option<usize> {
+        self.latest = None;
+        insert(&mut self.zen, run)
+    }
+
+    // Kept apart from the table, it doesn't compete with the other runs
+    pub fn submit_daily(&mut self, run: Run) {
+        self.latest = None;
+        self.daily.insert(0, run);
+        self.daily.truncate(MAX_ENTRIES);
+    }
+
+    pub fn name_latest(&mut self, name: String) {
+        if let Some(run) = self.latest.and_then(|place| self.runs.get_mut(place)) {
+            run.name = name;
+        }
+    }
+}
+
+// Every single player run added up, kept next to the high scores
+#[derive(Default, Clone, Copy)]
+pub struct Totals {
+    pub games: usize,
+    pub food: usize,
+    pub turns: usize,
+    // Cells moved
+    pub distance: usize,
+    // Seconds played
+    pub time: u32,
+}
+
+impl Totals {
+    fn to_line(self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.games, self.food, self.turns, self.distance, self.time
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        Some(Totals {
+            games: fields.next()?.parse().ok()?,
+            food: fields.next()?.parse().ok()?,
+            turns: fields.next()?.parse().ok()?,
+            distance: fields.next()?.parse().ok()?,
+            time: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+// Head positions of the best run, one per move, shown as a trail in later runs
+pub struct Ghost {
+    pub path: Vec<IVec2>,
+    // Picked on the options screen, the path is still kept while it's hidden
+    pub visible: bool,
+}
+
+impl Ghost {
+    // "x,y" pairs on a single line
+    fn to_line(&self) -> String {
+        let cells: Vec<_> = self
+            .path
+            .iter()
+            .map(|p| format!("{},{}", p.x, p.y))
+            .collect();
+        cells.join(" ")
+    }
+
+    fn from_line(line: &str) -> Option<Vec<IVec2>> {
+        line.split_whitespace()
+            .map(|cell| {
+                let (x, y) = cell.split_once(',')?;
+                Some(IVec2::new(x.parse().ok()?, y.parse().ok()?))
+            })
+            .collect()
+    }
+}
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let (mut runs, mut daily, mut zen) = (Vec::new(), Vec::new(), Vec::new());
+        for line in load(HIGH_SCORES).unwrap_or_default().lines() {
+            let (table, line) = match line.split_once(' ') {
+                Some((DAILY, line)) => (&mut daily, line),
+                Some((ZEN, line)) => (&mut zen, line),
+                _ => (&mut runs, line),
+            };
+            table.extend(Run::from_line(line));
+        }
+        let totals = load(TOTALS)
+            .and_then(|text| Totals::from_line(&text))
+            .unwrap_or_default();
+        let path = load(GHOST)
+            .and_then(|text| Ghost::from_line(&text))
+            .unwrap_or_default();
+        app.insert_resource(HighScores {
+            runs,
+            latest: None,
+            daily,
+            zen,
+        })
+        .insert_resource(totals)
+        .insert_resource(Ghost {
+            path,
+            visible: true,
+        })
+        .add_system(save_high_scores)
+        .add_system(save_totals)
+        .add_system(save_ghost);
+    }
+}
+
+fn tagged<'a>(tag: &'a str, runs: &'a [Run]) -> impl Iterator<Item = String> + 'a {
+    runs.iter()
+        .map(move |run| format!("{} {}", tag, run.to_line()))
+}
+
+fn save_high_scores(scores: Res<HighScores>) {
+    if scores.is_changed() && !scores.is_added() {
+        let lines: Vec<_> = scores
+            .runs
+            .iter()
+            .map(|run| run.to_line())
+            .chain(tagged(DAILY, &scores.daily))
+            .chain(tagged(ZEN, &scores.zen))
+            .collect();
+        save(HIGH_SCORES, &lines.join("\n"));
+    }
+}
+
+fn save_totals(totals: Res<Totals>) {
+    if totals.is_changed() && !totals.is_added() {
+        save(TOTALS, &totals.to_line());
+    }
+}
+
+// Showing or hiding it writes the same path again, which is rare enough not to matter
+fn save_ghost(ghost: Res<Ghost>) {
+    if ghost.is_changed() && !ghost.is_added() {
+        save(GHOST, &ghost.to_line());
+    }
+}
+
+pub fn now() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    #[cfg(target_arch = "wasm32")]
+    let secs = (js_sys::Date::now() / 1000.0) as u64;
+    secs
+}
+
+// Kept in the user's data directory, a missing or corrupt file counts as no runs yet
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    std::fs::read_to_string(path(name)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, text: &str) {
+    if let Some(path) = path(name) {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).ok();
+        }
+        std::fs::write(path, text).ok();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(name: &str) -> Option<std::path::PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("bevy_ascii_snake")
+            .join(format!("{}.txt", name)),
+    )
+}
+
+// The browser's local storage stands in for the files on the web
+#[cfg(target_arch = "wasm32")]
+fn storage_key(name: &str) -> String {
+    format!("bevy_ascii_snake.{}", name)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Option<String> {
+    storage()?.get_item(&storage_key(name)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(name: &str, text: &str) {
+    if let Some(storage) = storage() {
+        storage.set_item(&storage_key(name), text).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_with_an_unknown_difficulty_are_dropped() {
+        assert!(Run::from_line("10 5 60 0 9 1 AAA 0 -").is_none());
+        let run = Run::from_line("10 5 60 0 2 1 AAA 0 -").unwrap();
+        assert_eq!(run.difficulty, Difficulty::Hard);
+        // Older lines without a difficulty still load as Normal
+        let run = Run::from_line("10 5 60 0").unwrap();
+        assert_eq!(run.difficulty, Difficulty::Normal);
+    }
+
+    #[test]
+    fn unreadable_runs_are_dropped() {
+        assert!(Run::from_line("").is_none());
+        assert!(Run::from_line("10 5 60").is_none());
+        assert!(Run::from_line("ten 5 60 0").is_none());
+        assert!(Run::from_line("10 5 60 0 1 x").is_none());
+        // An unknown cause of death is only left out
+        let run = Run::from_line("10 5 60 0 1 2 AAA 1 Lava").unwrap();
+        assert_eq!((run.level, run.won, run.death), (2, true, None));
+        assert_eq!(run.name, "AAA");
+    }
+}
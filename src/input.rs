@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::controls::Controls;
+use crate::replay::Replay;
+use crate::snake::{AiSnake, Player, Steering};
+use crate::{GameSettings, GameState, DIRS};
+
+// Seconds without input on the title before the demo starts, and between its moves
+const DEMO_IDLE_TIME: f32 = 10.0;
+const DEMO_STEP: f32 = 0.1;
+// The demo starts over once its snake is this long
+const DEMO_LENGTH: usize = 25;
+const MAX_QUEUED_TURNS: usize = 3;
+
+// A bot playing behind the title after a while without input, in terminal coordinates.
+// The body is empty while there's no demo
+#[derive(Default)]
+pub struct Demo {
+    pub body: VecDeque<IVec2>,
+    pub food: IVec2,
+}
+
+impl Demo {
+    // A short snake in the middle heading right
+    pub fn start(&mut self, size: IVec2) {
+        let center = size / 2;
+        self.body = (0..3).map(|i| center - IVec2::X * i).collect();
+        self.place_food(size);
+    }
+
+    pub fn place_food(&mut self, size: IVec2) {
+        let free: Vec<_> = (1..size.y - 1)
+            .flat_map(|y| (1..size.x - 1).map(move |x| IVec2::new(x, y)))
+            .filter(|p| !self.body.contains(p))
+            .collect();
+        if let Some(food) = free.choose(&mut rand::thread_rng()) {
+            self.food = *food;
+        }
+    }
+
+    // Heads for the food along whichever free move gets closest, and starts over once
+    // it's trapped or long enough
+    pub fn step(&mut self, size: IVec2) {
+        let head = self.body[0];
+        let neck = self.body[1];
+        let inside = |p: IVec2| p.cmpge(IVec2::ONE).all() && p.cmplt(size - 1).all();
+        let next = DIRS
+            .iter()
+            .map(|dir| head + *dir)
+            .filter(|p| *p != neck && inside(*p) && !self.body.contains(p))
+            .min_by_key(|p| {
+                let d = (*p - self.food).abs();
+                d.x + d.y
+            });
+        let next = match next {
+            Some(next) if self.body.len() < DEMO_LENGTH => next,
+            _ => return self.start(size),
+        };
+        self.body.push_front(next);
+        if next == self.food {
+            self.place_food(size);
+        } else {
+            self.body.pop_back();
+        }
+    }
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct InputPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(input)
+                .with_system(pause),
+        )
+        .add_system_set(SystemSet::on_update(GameState::Paused).with_system(pause));
+        if !self.headless {
+            app.init_resource::<Demo>()
+                .add_system_to_stage(CoreStage::PreUpdate, run_demo.after(InputSystem));
+        }
+    }
+}
+
+// Runs before the title's own systems so the key that stops the demo only does that.
+// Not drawn from the game's rng so replays stay the same
+fn run_demo(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<GamepadButton>>,
+    mut demo: ResMut<Demo>,
+    q_term: Query<&Terminal>,
+    mut idle: Local<f32>,
+    mut step: Local<f32>,
+) {
+    let running = !demo.body.is_empty();
+    if state.current() != &GameState::Title {
+        *idle = 0.0;
+        if running {
+            demo.body.clear();
+        }
+        return;
+    }
+
+    let pressed =
+        keys.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some();
+    if pressed {
+        *idle = 0.0;
+        if running {
+            demo.body.clear();
+            keys.clear();
+            buttons.clear();
+        }
+        return;
+    }
+
+    let size = match q_term.get_single() {
+        Ok(term) => term.size().as_ivec2(),
+        Err(_) => return,
+    };
+    if !running {
+        *idle += time.delta_seconds();
+        if *idle >= DEMO_IDLE_TIME {
+            demo.start(size);
+        }
+        return;
+    }
+    *step += time.delta_seconds();
+    if *step >= DEMO_STEP {
+        *step = 0.0;
+        demo.step(size);
+    }
+}
+
+// Gameplay systems stop while `Paused` sits on top of `Playing`. A crash on the same frame
+// goes first
+pub(crate) fn pause(controls: Controls, mut state: ResMut<State<GameState>>) {
+    if controls.pause() {
+        let _ = match *state.current() == GameState::Paused {
+            true => state.pop(),
+            false => state.push(GameState::Paused),
+        };
+    }
+}
+
+pub(crate) fn input(
+    time: Res<Time>,
+    mut controls: Controls,
+    settings: Res<GameSettings>,
+    replay: Res<Replay>,
+    mut q_steering: Query<(&mut Steering, &Player), Without<AiSnake>>,
+) {
+    // Replays steer on their own
+    if replay.0.is_some() {
+        return;
+    }
+
+    let boosting = [controls.boost(), controls.second_boost()];
+    let dirs = [controls.dir(), controls.second_dir()];
+    let now = time.seconds_since_startup();
+    for (mut steering, player) in &mut q_steering {
+        steering.boosting = boosting[player.0];
+        let dir = dirs[player.0];
+        if dir == IVec2::ZERO {
+            continue;
+        }
+
+        let last = steering.turns.back().copied().unwrap_or(steering.dir);
+        // Key chatter can re-press a direction within a few frames, don't queue it twice
+        let repeated = dir == last && now - steering.turned_at < settings.repeat_window;
+        if dir != -last && !repeated && steering.turns.len() < MAX_QUEUED_TURNS {
+            steering.turns.push_back(dir);
+            steering.turned_at = now;
+        }
+    }
+}
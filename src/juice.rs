@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use bevy_ascii_terminal::TiledCamera;
+use serde::{Deserialize, Serialize};
+
+const SHAKE_TIME: f32 = 0.3;
+// Tiles the camera is thrown around by at the start of a shake
+const SHAKE_STRENGTH: f32 = 0.4;
+const FLASH_TIME: f32 = 0.5;
+const PULSE_TIME: f32 = 0.35;
+
+// Off for players who'd rather the screen didn't move, flashes still happen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenShake(pub bool);
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+// A bit of feedback that fades out over its timer, the entity goes away once it's done
+#[derive(Component)]
+pub struct Effect {
+    kind: EffectKind,
+    timer: Timer,
+}
+
+enum EffectKind {
+    Shake,
+    // Background of the border tiles
+    Flash(Color),
+    // Background of a single tile, in terminal coordinates
+    Pulse(IVec2, Color),
+}
+
+impl Effect {
+    fn new(kind: EffectKind, secs: f32) -> Self {
+        Self {
+            kind,
+            timer: Timer::from_seconds(secs, false),
+        }
+    }
+
+    pub fn shake() -> Self {
+        Self::new(EffectKind::Shake, SHAKE_TIME)
+    }
+
+    pub fn flash(color: Color) -> Self {
+        Self::new(EffectKind::Flash(color), FLASH_TIME)
+    }
+
+    pub fn pulse(pos: IVec2, color: Color) -> Self {
+        Self::new(EffectKind::Pulse(pos, color), PULSE_TIME)
+    }
+
+    // 1 when the effect starts down to 0 when it ends
+    fn strength(&self) -> f32 {
+        1.0 - self.timer.percent()
+    }
+}
+
+pub struct JuicePlugin;
+
+impl Plugin for JuicePlugin {
+    fn build(&self, app: &mut App) {
+        // Screens are drawn during the update, effects go on top of whatever they drew
+        app.init_resource::<ScreenShake>()
+            .add_system_to_stage(CoreStage::PostUpdate, shake)
+            .add_system_to_stage(CoreStage::PostUpdate, tint)
+            .add_system_to_stage(CoreStage::PostUpdate, expire.after(shake).after(tint));
+    }
+}
+
+// Offsets from all running shakes add up. Not drawn from the game's rng so replays stay
+// the same
+fn shake(
+    q_effect: Query<&Effect>,
+    mut q_cam: Query<&mut Transform, With<TiledCamera>>,
+    enabled: Res<ScreenShake>,
+    mut applied: Local<Vec2>,
+) {
+    let offset = q_effect
+        .iter()
+        .filter(|effect| matches!(effect.kind, EffectKind::Shake) && enabled.0)
+        .map(|effect| {
+            let dir = Vec2::new(rand::random(), rand::random()) * 2.0 - 1.0;
+            dir * SHAKE_STRENGTH * effect.strength()
+        })
+        .fold(Vec2::ZERO, |sum, offset| sum + offset);
+    for mut transform in &mut q_cam {
+        transform.translation += (offset - *applied).extend(0.0);
+    }
+    *applied = offset;
+}
+
+// Colors fade to the black background, the last frame leaves the tiles as they were
+fn tint(q_effect: Query<&Effect>, mut q_term: Query<&mut Terminal>) {
+    let mut term = match q_term.get_single_mut() {
+        Ok(term) => term,
+        Err(_) => return,
+    };
+    for effect in &q_effect {
+        let fade = |color: Color| {
+            let rgb = Vec4::from(color).truncate() * effect.strength();
+            Color::from(rgb.extend(1.0))
+        };
+        match effect.kind {
+            EffectKind::Shake => {}
+            EffectKind::Flash(color) => {
+                let color = fade(color);
+                let size = term.size().as_ivec2();
+                let border = (0..size.x)
+                    .flat_map(|x| [[x, 0], [x, size.y - 1]])
+                    .chain((0..size.y).flat_map(|y| [[0, y], [size.x - 1, y]]));
+                for pos in border {
+                    term.put_color(pos, color.bg());
+                }
+            }
+            EffectKind::Pulse(pos, color) => {
+                if term.is_in_bounds(pos) {
+                    term.put_color(pos, fade(color).bg());
+                }
+            }
+        }
+    }
+}
+
+fn expire(time: Res<Time>, mut q_effect: Query<(Entity, &mut Effect)>, mut commands: Commands) {
+    for (entity, mut effect) in &mut q_effect {
+        // Finished effects were drawn at zero strength this frame, which undid them
+        if effect.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+        effect.timer.tick(time.delta());
+    }
+}
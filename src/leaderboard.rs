@@ -0,0 +1,273 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+use bevy_ascii_terminal::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controls::Controls;
+use crate::locale::{self, Localization};
+use crate::practice::Practice;
+use crate::render::Theme;
+use crate::replay::{Recording, Replay};
+use crate::ui::NameEntry;
+use crate::{GameState, Players, RunStats, Score};
+
+const TOP: usize = 20;
+// Used when `snake.toml` doesn't set `leaderboard_url`, which is always the case on the web
+const BUILT_IN_URL: Option<&str> = option_env!("SNAKE_LEADERBOARD_URL");
+
+// Posted as json to the endpoint. The seed and a hash of the replay let the server check
+// the run against the recording if it has a way to get hold of it
+#[derive(Serialize)]
+struct Submission {
+    name: String,
+    score: usize,
+    seed: u64,
+    replay: String,
+}
+
+// The endpoint answers a GET with a json array of these, best first
+#[derive(Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+}
+
+#[derive(Default)]
+enum Fetch {
+    #[default]
+    Loading,
+    Done(Vec<Entry>),
+    Failed(String),
+}
+
+// Runs are sent from the io task pool and the top runs come back through `fetch` whenever
+// the request finishes, so the game never waits on the network
+pub struct Leaderboard {
+    url: Option<String>,
+    // Held back while the run is being named
+    pending: Option<Submission>,
+    fetch: Arc<Mutex<Fetch>>,
+}
+
+// Only added with the `leaderboard` feature. Without an endpoint nothing is ever sent
+pub struct LeaderboardPlugin {
+    pub url: Option<String>,
+}
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboard {
+            url: self.url.clone().or(BUILT_IN_URL.map(String::from)),
+            pending: None,
+            fetch: default(),
+        })
+        .add_system_set(SystemSet::on_update(GameState::Title).with_system(open_leaderboard))
+        .add_system_set(
+            SystemSet::on_enter(GameState::GameOver)
+                .with_system(queue_submission.after(crate::record_run)),
+        )
+        .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(submit))
+        .add_system_set(SystemSet::on_enter(GameState::Leaderboard).with_system(fetch_leaderboard))
+        .add_system_set(
+            SystemSet::on_update(GameState::Leaderboard)
+                .with_system(render_leaderboard)
+                .with_system(close_leaderboard),
+        );
+    }
+}
+
+// Single player runs only, and not the ones watched back, continued from a save or practiced
+fn queue_submission(
+    players: Res<Players>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    replay: Res<Replay>,
+    recording: Res<Recording>,
+    practice: Res<Practice>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    let played_through = replay.0.is_none() && !stats.resumed && !practice.enabled;
+    if *players != Players::One || !played_through || score.0[0] == 0 {
+        return;
+    }
+    leaderboard.pending = Some(Submission {
+        name: String::new(),
+        score: score.0[0],
+        seed: recording.seed,
+        replay: hash(&recording.to_text()),
+    });
+}
+
+// Doesn't run while the name is being typed over the game over screen, so the run goes
+// out with whatever name it ends up with
+fn submit(entry: Res<NameEntry>, mut leaderboard: ResMut<Leaderboard>) {
+    let url = match &leaderboard.url {
+        Some(url) => url.clone(),
+        None => return,
+    };
+    let mut submission = match leaderboard.pending.take() {
+        Some(submission) => submission,
+        None => return,
+    };
+    submission.name = match entry.name.is_empty() {
+        true => "ANON".to_string(),
+        false => entry.name.iter().collect(),
+    };
+    let body = match serde_json::to_string(&submission) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(err) = request("POST", &url, Some(body)).await {
+                warn!("Couldn't submit to the leaderboard: {}", err);
+            }
+        })
+        .detach();
+}
+
+// The title screen's other keys may have changed the state already this frame
+fn open_leaderboard(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(KeyCode::G) {
+        let _ = state.push(GameState::Leaderboard);
+    }
+}
+
+fn fetch_leaderboard(mut leaderboard: ResMut<Leaderboard>) {
+    let url = match &leaderboard.url {
+        Some(url) => format!("{}?limit={}", url, TOP),
+        None => {
+            leaderboard.fetch = Arc::new(Mutex::new(Fetch::Failed("No leaderboard".into())));
+            return;
+        }
+    };
+    // A new slot each time, so an earlier request finishing late can't overwrite it
+    let fetch = Arc::new(Mutex::new(Fetch::Loading));
+    leaderboard.fetch = fetch.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            let result = match request("GET", &url, None).await {
+                Ok(text) => serde_json::from_str(&text)
+                    .map(Fetch::Done)
+                    .unwrap_or_else(|err| Fetch::Failed(err.to_string())),
+                Err(err) => Fetch::Failed(err),
+            };
+            *fetch.lock().unwrap() = result;
+        })
+        .detach();
+}
+
+// Redrawn every frame until the request is back
+fn render_leaderboard(
+    leaderboard: Res<Leaderboard>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let title = loc.get("leaderboard.title");
+    term.put_string(
+        [locale::centered(&title), 13].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    match &*leaderboard.fetch.lock().unwrap() {
+        Fetch::Loading => {
+            let text = loc.get("leaderboard.loading");
+            term.put_string(
+                [locale::centered(&text), 0].pivot(Pivot::Center),
+                text.fg(colors.faded),
+            );
+        }
+        Fetch::Failed(err) => {
+            let text = loc.format("leaderboard.failed", &[err]);
+            let text: String = text.chars().take(36).collect();
+            term.put_string(
+                [locale::centered(&text), 0].pivot(Pivot::Center),
+                text.fg(colors.faded),
+            );
+        }
+        Fetch::Done(entries) => {
+            for (i, entry) in entries.iter().take(TOP).enumerate() {
+                let name: String = entry.name.chars().take(12).collect();
+                let line = format!("{:>2}. {:<12} {:>8}", i + 1, name, entry.score);
+                let y = 11 - i as i32;
+                term.put_string([-13, y].pivot(Pivot::Center), line.fg(colors.text));
+            }
+            if entries.is_empty() {
+                let text = loc.get("leaderboard.empty");
+                term.put_string(
+                    [locale::centered(&text), 0].pivot(Pivot::Center),
+                    text.fg(colors.faded),
+                );
+            }
+        }
+    }
+}
+
+fn close_leaderboard(
+    input: Res<Input<KeyCode>>,
+    controls: Controls,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) || controls.confirm() {
+        state.pop().unwrap();
+    }
+}
+
+// FNV-1a, which is the same on every platform and build
+fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// Blocking, but it only ever holds up one of the io threads
+#[cfg(not(target_arch = "wasm32"))]
+async fn request(method: &str, url: &str, body: Option<String>) -> Result<String, String> {
+    let request = ureq::request(method, url).set("Content-Type", "application/json");
+    let response = match body {
+        Some(body) => request.send_string(&body),
+        None => request.call(),
+    };
+    response
+        .map_err(|err| err.to_string())?
+        .into_string()
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn request(method: &str, url: &str, body: Option<String>) -> Result<String, String> {
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    let error = |err: JsValue| format!("{:?}", err);
+    let mut init = web_sys::RequestInit::new();
+    init.method(method);
+    if let Some(body) = body {
+        init.body(Some(&JsValue::from_str(&body)));
+    }
+    let request = web_sys::Request::new_with_str_and_init(url, &init).map_err(error)?;
+    request
+        .headers()
+        .set("Content-Type", "application/json")
+        .map_err(error)?;
+    let window = web_sys::window().ok_or("No window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(error)?
+        .dyn_into()
+        .map_err(error)?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let text = JsFuture::from(response.text().map_err(error)?)
+        .await
+        .map_err(error)?;
+    text.as_string().ok_or_else(|| "Not text".to_string())
+}
@@ -0,0 +1,1729 @@
+// bevy systems routinely take more params than clippy likes
+#![allow(clippy::too_many_arguments)]
+
+mod accessibility;
+mod achievements;
+mod announce;
+pub mod audio;
+mod combo;
+mod controls;
+mod crash;
+mod daily;
+mod editor;
+pub mod food;
+pub mod headless;
+mod high_score;
+pub mod input;
+mod juice;
+#[cfg(feature = "leaderboard")]
+mod leaderboard;
+mod locale;
+mod music;
+mod options;
+mod particles;
+mod practice;
+pub mod render;
+mod replay;
+mod settings;
+pub mod snake;
+mod speedrun;
+mod suspend;
+mod touch;
+pub mod ui;
+mod victory;
+mod volume;
+mod window;
+mod zen;
+
+use accessibility::{Accessibility, AccessibilityPlugin};
+use achievements::AchievementsPlugin;
+use announce::AnnouncePlugin;
+use audio::SoundPlugin;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::DefaultPlugins;
+use bevy_ascii_terminal::prelude::*;
+use bevy_kira_audio::AudioPlugin;
+use combo::ComboPlugin;
+use controls::{ControlsPlugin, KeyBindings};
+use crash::CrashPlugin;
+use daily::{Daily, DailyPlugin};
+use editor::{CustomLevel, EditorPlugin};
+use food::{FoodCount, FoodPlugin, FoodTarget, RiskBonus};
+use headless::HeadlessPlugin;
+use high_score::{Ghost, HighScorePlugin, HighScores, Run, Totals};
+use input::InputPlugin;
+use juice::JuicePlugin;
+use locale::Localization;
+use music::MusicPlugin;
+use particles::ParticlesPlugin;
+use practice::{Practice, PracticePlugin};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use render::{RenderPlugin, Theme};
+use replay::{Recording, Replay, ReplayPlugin};
+use serde::{Deserialize, Serialize};
+use snake::{DeathCause, SnakePlugin};
+use speedrun::SpeedrunPlugin;
+use std::marker::PhantomData;
+use suspend::SuspendPlugin;
+use touch::TouchPlugin;
+use ui::UiPlugin;
+use victory::VictoryPlugin;
+use volume::{AudioSettings, VolumePlugin};
+use window::WindowPlugin;
+use zen::{Zen, ZenPlugin};
+
+const STAGE_SIZE: IVec2 = IVec2::from_array([40, 40]);
+const START_SPEED: f32 = 8.0;
+const ACCELERATION: f32 = 0.35;
+const MAX_SPEED: f32 = 35.;
+const SPEED_TIERS: f32 = 5.;
+const HEAD_GLYPH: char = '@';
+const BODY_GLYPH: char = '█';
+const FOOD_GLYPH: char = '☼';
+const BONUS_GLYPH: char = '♦';
+const BONUS_CHANCE: f64 = 0.15;
+const BONUS_VALUE: usize = 3;
+const BONUS_TICKS: usize = 40;
+const POISON_GLYPH: char = 'x';
+const POISON_CHANCE: f64 = 0.2;
+const POISON_SHRINK: usize = 2;
+const ROTTEN_GLYPH: char = '%';
+const SPOIL_TICKS: usize = 150;
+const ROT_TICKS: usize = 50;
+const POWER_UP_CHANCE: f64 = 0.1;
+const POWER_UP_TICKS: usize = 60;
+const POWER_UP_SHRINK: usize = 4;
+const SLOW_FACTOR: f32 = 0.5;
+const WALL_GLYPH: char = '#';
+const PORTAL_GLYPH: char = '◙';
+const CRITTER_TICKS: usize = 3;
+const WALL_COUNT: usize = 6;
+const HAZARD_GLYPH: char = '▒';
+const SHRINK_TICKS: usize = 120;
+// Moves before a shrink that the closing ring flashes for
+const SHRINK_WARNING: usize = 12;
+// Cells from the middle to the edge that the arena never shrinks past
+const MIN_ARENA: i32 = 4;
+const SPEEDRUN_LENGTH: usize = 30;
+const WIN_FILL: f32 = 1.0;
+const COMBO_TICKS: usize = 30;
+const MAX_COMBO: usize = 5;
+const NEAR_MISS_POINTS: usize = 1;
+const COUNTDOWN_TIME: f32 = 3.0;
+const AUTO_RESTART_TIME: f32 = 5.0;
+const REPEAT_WINDOW: f64 = 0.03;
+const BOOST_FACTOR: f32 = 2.0;
+const BOOST_SHRINK_TICKS: usize = 8;
+const LEVEL_FOOD: usize = 10;
+const LEVEL_SPEED: f32 = 1.5;
+const LEVEL_BANNER_TIME: f32 = 2.0;
+const DIRS: [IVec2; 4] = [IVec2::Y, IVec2::NEG_Y, IVec2::NEG_X, IVec2::X];
+
+#[derive(Debug, StageLabel, Clone, Eq, PartialEq, Hash)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+    Bindings,
+    Options,
+    // Opened from the options
+    Accessibility,
+    // Shows the next level's number over `Playing` before play carries on
+    LevelUp,
+    // Naming a run that made the high score table, over `GameOver`
+    TextInput,
+    Achievements,
+    // Saved levels, opened with E on the title screen
+    Levels,
+    // Opened from the level list
+    Editor,
+    #[cfg(feature = "leaderboard")]
+    Leaderboard,
+}
+
+// The game as the binary runs it, or only the rules without a window, terminal or audio
+// for tests and benchmarks. Headless apps are stepped by hand, see `headless::tick`
+pub fn build_game_app(headless: bool) -> App {
+    let mut app = App::new();
+    if headless {
+        app.add_plugin(HeadlessPlugin);
+        return app;
+    }
+
+    let args: Vec<_> = std::env::args().collect();
+    let seed = args
+        .iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|seed| seed.parse().ok());
+    app.add_plugin(WindowPlugin)
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TerminalPlugin)
+        .add_plugin(AudioPlugin)
+        .add_plugin(GamePlugin {
+            // Kiosk and demo setups can leave the game running unattended
+            auto_restart: args.iter().any(|arg| arg == "--auto-restart"),
+            seed,
+            ..default()
+        });
+    app
+}
+
+// The whole game, the host app provides the window, terminal and audio plugins
+// Stage size and seed given here take precedence over `snake.toml`
+#[derive(Default)]
+pub struct GamePlugin {
+    pub stage_size: Option<IVec2>,
+    // Start a new game by itself a few seconds after game over
+    pub auto_restart: bool,
+    pub seed: Option<u64>,
+}
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        let restart_timer = self
+            .auto_restart
+            .then(|| Timer::from_seconds(AUTO_RESTART_TIME, false));
+        let file = settings::load();
+        let options = options::load();
+        // Colors from the settings file win over the palette picked in game
+        let theme = Theme {
+            palette: options.palette,
+            body_colors: file.body_colors().unwrap_or(options.palette.body_colors()),
+            smooth: options.smooth,
+            ..default()
+        };
+
+        app.add_plugin(HighScorePlugin)
+            .add_plugin(ControlsPlugin)
+            .add_plugin(VolumePlugin)
+            .add_plugin(MusicPlugin)
+            .add_plugin(JuicePlugin)
+            .add_plugin(TouchPlugin)
+            .add_plugin(AchievementsPlugin)
+            .add_plugin(SpeedrunPlugin::default())
+            .add_plugin(ParticlesPlugin)
+            .add_plugin(SuspendPlugin)
+            .add_plugin(ReplayPlugin)
+            .add_plugin(VictoryPlugin::default())
+            .add_plugin(PracticePlugin::default())
+            .add_plugin(AccessibilityPlugin)
+            .add_plugin(AnnouncePlugin)
+            .add_plugin(CrashPlugin::default())
+            .add_plugin(EditorPlugin)
+            .add_plugin(DailyPlugin)
+            .add_plugin(ComboPlugin)
+            .add_plugin(ZenPlugin::default());
+        file.apply_bindings(&mut app.world.resource_mut::<KeyBindings>());
+        file.apply_audio(&mut app.world.resource_mut::<AudioSettings>());
+        app.world.resource_mut::<Ghost>().visible = options.show_ghost;
+        app.insert_resource(
+            GameSettings {
+                stage_size: self.stage_size.unwrap_or(file.game.stage_size),
+                seed: self.seed.or(file.game.seed),
+                ..file.game
+            }
+            .checked(),
+        )
+        .init_resource::<GameRng>()
+        .init_resource::<FoodCount>()
+        .insert_resource(file.max_food.map_or_else(FoodTarget::default, FoodTarget))
+        .init_resource::<Score>()
+        .init_resource::<RunStats>()
+        .init_resource::<Countdown>()
+        .init_resource::<RiskBonus>()
+        .insert_resource(theme)
+        .insert_resource(Localization::new(options.language))
+        .insert_resource(Accessibility {
+            shapes: options.shapes,
+            speed_floor: options.speed_floor,
+            announce: options.announce,
+        })
+        .insert_resource(options.wall_mode)
+        .insert_resource(options.screen_shake)
+        .insert_resource(options.touch_mode)
+        .init_resource::<Difficulty>()
+        .init_resource::<Layout>()
+        .init_resource::<StageSize>()
+        .init_resource::<GameMode>()
+        .init_resource::<ShrinkingBounds>()
+        .insert_resource(options.players)
+        .init_resource::<Winner>()
+        .init_resource::<Level>()
+        .insert_resource(AutoRestart(restart_timer))
+        .add_state(GameState::Title)
+        .add_plugin(SoundPlugin)
+        .add_plugin(InputPlugin::default())
+        .add_plugin(SnakePlugin)
+        .add_plugin(FoodPlugin)
+        .add_plugin(RenderPlugin)
+        .add_plugin(UiPlugin::default())
+        .init_resource::<Picks>()
+        .add_system_set(
+            SystemSet::on_enter(GameState::Playing).with_system(keep_picks.before(snake::spawn)),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(record_run));
+        #[cfg(feature = "leaderboard")]
+        app.add_plugin(leaderboard::LeaderboardPlugin {
+            url: file.leaderboard_url.clone(),
+        });
+    }
+}
+
+// Every tunable the game systems read, so they can be changed without recompiling,
+// loaded from `snake.toml` when it's there
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct GameSettings {
+    pub stage_size: IVec2,
+    pub start_speed: f32,
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub head_glyph: char,
+    pub body_glyph: char,
+    pub food_glyph: char,
+    pub bonus_glyph: char,
+    pub bonus_chance: f64,
+    pub bonus_value: usize,
+    pub bonus_ticks: usize,
+    pub poison_glyph: char,
+    // Chance of a poison food appearing alongside each regular one
+    pub poison_chance: f64,
+    pub poison_shrink: usize,
+    pub rotten_glyph: char,
+    // Moves before regular food spoils, 0 keeps it fresh, and how long it lies around rotten
+    pub spoil_ticks: usize,
+    pub rot_ticks: usize,
+    // Chance of a power-up appearing alongside each regular food, it lasts `bonus_ticks`
+    // on the board and its effect lasts `power_up_ticks` moves
+    pub power_up_chance: f64,
+    pub power_up_ticks: usize,
+    pub power_up_shrink: usize,
+    // Speed multiplier while `SlowDown` is active
+    pub slow_factor: f32,
+    pub wall_glyph: char,
+    pub portal_glyph: char,
+    // Moves of the first snake between each step the critters take
+    pub critter_ticks: usize,
+    // Number of wall segments scattered over the stage each game
+    pub wall_count: usize,
+    pub hazard_glyph: char,
+    // Moves between the edge closing in by a ring in the shrinking arena. Counted in moves
+    // rather than seconds so replays shrink at the same points
+    pub shrink_ticks: usize,
+    // Seconds after a turn during which pressing the same direction again is ignored
+    pub repeat_window: f64,
+    // Seconds the snake waits before moving at the start of each game
+    pub countdown: f32,
+    // Speed multiplier while boost is held, one tail segment is lost every `boost_shrink_ticks`
+    pub boost_factor: f32,
+    pub boost_shrink_ticks: usize,
+    // Seeds every game the same way when set, so runs can be repeated
+    pub seed: Option<u64>,
+    // Food eaten to reach each next level, 0 stays on the first one
+    pub level_food: usize,
+    // Added to the start speed on every level past the first
+    pub level_speed: f32,
+    // Length that ends a speedrun
+    pub speedrun_length: usize,
+    // Share of the open stage a snake has to cover to win, walls and the hazard ring
+    // don't count
+    pub win_fill: f32,
+    // Moves after eating that the next food builds the combo, 0 turns combos off, and the
+    // highest multiplier it builds to
+    pub combo_ticks: usize,
+    pub max_combo: usize,
+    // Points for the head coming within a cell of the body
+    pub near_miss_points: usize,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            stage_size: STAGE_SIZE,
+            start_speed: START_SPEED,
+            acceleration: ACCELERATION,
+            max_speed: MAX_SPEED,
+            head_glyph: HEAD_GLYPH,
+            body_glyph: BODY_GLYPH,
+            food_glyph: FOOD_GLYPH,
+            bonus_glyph: BONUS_GLYPH,
+            bonus_chance: BONUS_CHANCE,
+            bonus_value: BONUS_VALUE,
+            bonus_ticks: BONUS_TICKS,
+            poison_glyph: POISON_GLYPH,
+            poison_chance: POISON_CHANCE,
+            poison_shrink: POISON_SHRINK,
+            rotten_glyph: ROTTEN_GLYPH,
+            spoil_ticks: SPOIL_TICKS,
+            rot_ticks: ROT_TICKS,
+            power_up_chance: POWER_UP_CHANCE,
+            power_up_ticks: POWER_UP_TICKS,
+            power_up_shrink: POWER_UP_SHRINK,
+            slow_factor: SLOW_FACTOR,
+            wall_glyph: WALL_GLYPH,
+            portal_glyph: PORTAL_GLYPH,
+            critter_ticks: CRITTER_TICKS,
+            wall_count: WALL_COUNT,
+            hazard_glyph: HAZARD_GLYPH,
+            shrink_ticks: SHRINK_TICKS,
+            repeat_window: REPEAT_WINDOW,
+            countdown: COUNTDOWN_TIME,
+            boost_factor: BOOST_FACTOR,
+            boost_shrink_ticks: BOOST_SHRINK_TICKS,
+            seed: None,
+            level_food: LEVEL_FOOD,
+            level_speed: LEVEL_SPEED,
+            speedrun_length: SPEEDRUN_LENGTH,
+            win_fill: WIN_FILL,
+            combo_ticks: COMBO_TICKS,
+            max_combo: MAX_COMBO,
+            near_miss_points: NEAR_MISS_POINTS,
+        }
+    }
+}
+
+impl GameSettings {
+    // The stage spans `1 - stage_size / 2..=stage_size / 2` on each axis
+    pub fn in_bounds(&self, p: IVec2) -> bool {
+        let half_stage = self.stage_size / 2;
+
+        !(p.cmple(-half_stage).any() || p.cmpge(half_stage + 1).any())
+    }
+
+    pub fn stage_cells(&self) -> impl Iterator<Item = IVec2> {
+        let half_stage = self.stage_size / 2;
+        (1 - half_stage.y..=half_stage.y)
+            .flat_map(move |y| (1 - half_stage.x..=half_stage.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    // 1 at the starting speed up to `SPEED_TIERS` at the top speed
+    pub fn speed_tier(&self, speed: f32) -> usize {
+        let range = (self.max_speed - self.start_speed).max(f32::EPSILON);
+        let progress = ((speed - self.start_speed) / range).clamp(0.0, 1.0);
+        (progress * (SPEED_TIERS - 1.)).floor() as usize + 1
+    }
+
+    // Terminal position of a stage cell, the stage sits inside the border
+    pub fn to_term(&self, p: IVec2) -> IVec2 {
+        p + self.stage_size / 2
+    }
+}
+
+// Reseeded every game so a recording can reproduce the same walls and food
+pub struct GameRng(pub StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+// Restarts the game once it runs out on the game over screen, `None` waits for input
+pub struct AutoRestart(pub Option<Timer>);
+
+// Holds the snake still while it runs, turns pressed meanwhile are still queued
+#[derive(Default)]
+pub struct Countdown(pub Timer);
+
+// Levels count from 1, the banner is shown between them
+pub struct Level {
+    pub number: usize,
+    pub banner: Timer,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            banner: Timer::from_seconds(LEVEL_BANNER_TIME, false),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+
+    pub fn index(self) -> usize {
+        Difficulty::ALL.iter().position(|d| *d == self).unwrap()
+    }
+
+    pub fn next(self) -> Self {
+        Difficulty::ALL[(self.index() + 1) % Difficulty::ALL.len()]
+    }
+
+    // Starting speed, acceleration per food and top speed
+    pub fn speeds(self) -> (f32, f32, f32) {
+        match self {
+            Difficulty::Easy => (5.0, 0.1, 20.),
+            Difficulty::Normal => (START_SPEED, ACCELERATION, MAX_SPEED),
+            Difficulty::Hard => (12.0, 0.8, 45.),
+            Difficulty::Insane => (16.0, 1.2, 60.),
+        }
+    }
+
+    // Easy plays on a roomier stage than the configured one
+    pub fn extra_space(self) -> IVec2 {
+        match self {
+            Difficulty::Easy => IVec2::splat(10),
+            _ => IVec2::ZERO,
+        }
+    }
+
+    // Easy leaves the stage empty whatever the layout
+    pub fn walls(self) -> bool {
+        self != Difficulty::Easy
+    }
+
+    // Rats and spiders let loose on each layout
+    pub fn critters(self) -> usize {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+            Difficulty::Insane => 3,
+        }
+    }
+
+    // How far the computer snake looks for food, past that it just stays out of trouble
+    pub fn ai_lookahead(self) -> usize {
+        match self {
+            Difficulty::Easy => 4,
+            Difficulty::Normal => 12,
+            Difficulty::Hard | Difficulty::Insane => usize::MAX,
+        }
+    }
+}
+
+// Writes the preset into the settings. The stage size moves by the difference between the
+// two presets so a size from the settings file is kept
+fn set_difficulty(current: &mut Difficulty, settings: &mut GameSettings, difficulty: Difficulty) {
+    let (start_speed, acceleration, max_speed) = difficulty.speeds();
+    settings.start_speed = start_speed;
+    settings.acceleration = acceleration;
+    settings.max_speed = max_speed;
+    settings.stage_size += difficulty.extra_space() - current.extra_space();
+    *current = difficulty;
+}
+
+// What the player picked on the title screen. Replays, daily and zen games swap in their own
+// settings, these are put back for each new game and once the title screen is back
+#[derive(Clone)]
+pub(crate) struct Picked {
+    settings: GameSettings,
+    difficulty: Difficulty,
+    stage_size: StageSize,
+    layout: Layout,
+    wall_mode: WallMode,
+}
+
+#[derive(Default)]
+pub(crate) struct Picks(Option<Picked>);
+
+#[derive(SystemParam)]
+pub(crate) struct Pickable<'w, 's> {
+    picks: ResMut<'w, Picks>,
+    settings: ResMut<'w, GameSettings>,
+    difficulty: ResMut<'w, Difficulty>,
+    stage_size: ResMut<'w, StageSize>,
+    layout: ResMut<'w, Layout>,
+    wall_mode: ResMut<'w, WallMode>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Pickable<'w, 's> {
+    fn picked(&self) -> Picked {
+        Picked {
+            settings: self.settings.clone(),
+            difficulty: *self.difficulty,
+            stage_size: *self.stage_size,
+            layout: *self.layout,
+            wall_mode: *self.wall_mode,
+        }
+    }
+
+    fn put_back(&mut self, picked: Picked) {
+        *self.settings = picked.settings;
+        *self.difficulty = picked.difficulty;
+        *self.stage_size = picked.stage_size;
+        *self.layout = picked.layout;
+        *self.wall_mode = picked.wall_mode;
+    }
+}
+
+pub(crate) fn keep_picks(mut pickable: Pickable) {
+    match pickable.picks.0.clone() {
+        Some(picked) => pickable.put_back(picked),
+        None => pickable.picks.0 = Some(pickable.picked()),
+    }
+}
+
+pub(crate) fn restore_picks(mut pickable: Pickable) {
+    if let Some(picked) = pickable.picks.0.take() {
+        pickable.put_back(picked);
+    }
+}
+
+// Walls placed on the stage at the start of each game
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    // `wall_count` short segments scattered around
+    #[default]
+    Random,
+    Box,
+    Cross,
+    Maze,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 4] = [Layout::Random, Layout::Box, Layout::Cross, Layout::Maze];
+
+    pub fn index(self) -> usize {
+        Layout::ALL.iter().position(|l| *l == self).unwrap()
+    }
+
+    pub fn next(self) -> Self {
+        Layout::ALL[(self.index() + 1) % Layout::ALL.len()]
+    }
+}
+
+// Picked on the title screen
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    // See `ShrinkingBounds`
+    Shrinking,
+    // See `Speedrun`
+    Speedrun,
+    // See `Practice`
+    Practice,
+    // See `Daily`
+    Daily,
+    // See `Zen`
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 6] = [
+        GameMode::Classic,
+        GameMode::Shrinking,
+        GameMode::Speedrun,
+        GameMode::Practice,
+        GameMode::Daily,
+        GameMode::Zen,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = GameMode::ALL.iter().position(|m| *m == self).unwrap();
+        GameMode::ALL[(i + 1) % GameMode::ALL.len()]
+    }
+}
+
+// Shrinking arena mode. Rings of the stage closed off so far kill like walls
+#[derive(Default)]
+pub struct ShrinkingBounds {
+    pub enabled: bool,
+    pub rings: i32,
+    // Ticks of the recording, when the next ring closes and the last one seen
+    pub next: usize,
+    pub tick: usize,
+}
+
+impl ShrinkingBounds {
+    pub fn is_safe(&self, p: IVec2, settings: &GameSettings) -> bool {
+        Self::inside(p, settings, self.rings)
+    }
+
+    // On the ring that closes next, while it's flashing
+    pub fn is_closing(&self, p: IVec2, settings: &GameSettings) -> bool {
+        let warning = self.enabled
+            && self.can_shrink(settings)
+            && self.next.saturating_sub(self.tick) <= SHRINK_WARNING
+            && self.tick % 4 < 2;
+        warning && self.is_safe(p, settings) && !Self::inside(p, settings, self.rings + 1)
+    }
+
+    pub fn can_shrink(&self, settings: &GameSettings) -> bool {
+        settings.stage_size.min_element() / 2 - self.rings > MIN_ARENA
+    }
+
+    pub fn inside(p: IVec2, settings: &GameSettings, rings: i32) -> bool {
+        let half_stage = settings.stage_size / 2;
+        !(p.cmple(rings - half_stage).any() || p.cmpge(half_stage + 1 - rings).any())
+    }
+}
+
+// Picked on the title screen, grows or shrinks whatever stage the settings give
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageSize {
+    Small,
+    #[default]
+    Normal,
+    Large,
+}
+
+impl StageSize {
+    pub const ALL: [StageSize; 3] = [StageSize::Small, StageSize::Normal, StageSize::Large];
+
+    pub fn index(self) -> usize {
+        StageSize::ALL.iter().position(|s| *s == self).unwrap()
+    }
+
+    pub fn next(self) -> Self {
+        StageSize::ALL[(self.index() + 1) % StageSize::ALL.len()]
+    }
+
+    pub fn extra_space(self) -> IVec2 {
+        match self {
+            StageSize::Small => IVec2::splat(-10),
+            StageSize::Normal => IVec2::ZERO,
+            StageSize::Large => IVec2::splat(10),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallMode {
+    #[default]
+    Solid,
+    // Leaving the stage enters it again from the opposite edge
+    Wrap,
+}
+
+impl WallMode {
+    pub const ALL: [WallMode; 2] = [WallMode::Solid, WallMode::Wrap];
+
+    pub fn toggle(self) -> Self {
+        match self {
+            WallMode::Solid => WallMode::Wrap,
+            WallMode::Wrap => WallMode::Solid,
+        }
+    }
+
+    pub fn wrap(self, p: IVec2, stage_size: IVec2) -> IVec2 {
+        match self {
+            WallMode::Solid => p,
+            WallMode::Wrap => {
+                let min = IVec2::ONE - stage_size / 2;
+                let x = (p.x - min.x).rem_euclid(stage_size.x);
+                let y = (p.y - min.y).rem_euclid(stage_size.y);
+                IVec2::new(x, y) + min
+            }
+        }
+    }
+}
+
+// Points for each player, only the first is used with a single snake
+#[derive(Default)]
+pub struct Score(pub [usize; 2]);
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Players {
+    #[default]
+    One,
+    Two,
+    // The second snake steers itself
+    Computer,
+}
+
+impl Players {
+    pub const ALL: [Players; 3] = [Players::One, Players::Two, Players::Computer];
+
+    pub fn next(self) -> Self {
+        match self {
+            Players::One => Players::Two,
+            Players::Two => Players::Computer,
+            Players::Computer => Players::One,
+        }
+    }
+
+    // Short name for the player's score and the winner announcement
+    pub fn name(self, player: usize, loc: &Localization) -> String {
+        match (self, player) {
+            (Players::Computer, 1) => loc.get("players.cpu"),
+            (_, 0) => loc.get("players.p1"),
+            _ => loc.get("players.p2"),
+        }
+    }
+}
+
+// The player left standing when a two player game ends, `None` if both went down together
+#[derive(Default)]
+pub struct Winner(pub Option<usize>);
+
+// Summary of the current run for the game over screen
+#[derive(Default)]
+pub struct RunStats {
+    // Seconds spent moving, the countdown and pauses don't count
+    pub time: f32,
+    pub top_speed: f32,
+    pub length: usize,
+    pub food: usize,
+    pub turns: usize,
+    // Cells moved
+    pub distance: usize,
+    // Where the first snake's head went, to become the ghost if the run is the best
+    pub path: Vec<IVec2>,
+    // Continued from a suspended game, so the recording and the path start partway in
+    pub resumed: bool,
+    // Ended by covering `win_fill` of the stage rather than by crashing
+    pub won: bool,
+    // What the first snake crashed into
+    pub death: Option<DeathCause>,
+    pub best_combo: usize,
+    pub near_misses: usize,
+}
+
+// Two player games aren't comparable with the table, and replays can't play them back.
+// Neither are practice runs, which can take steps back. Replays don't know about custom
+// levels either, or zen games, so games on either aren't kept. Daily and zen games go in
+// their own tables, daily ones once a day
+fn record_run(
+    mut high_scores: ResMut<HighScores>,
+    players: Res<Players>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    recording: Res<Recording>,
+    replay: Res<Replay>,
+    difficulty: Res<Difficulty>,
+    level: Res<Level>,
+    mut totals: ResMut<Totals>,
+    mut ghost: ResMut<Ghost>,
+    mut state: ResMut<State<GameState>>,
+    practice: Res<Practice>,
+    custom: Res<CustomLevel>,
+    (daily, mut zen): (Res<Daily>, ResMut<Zen>),
+) {
+    zen.latest = None;
+    if *players != Players::One || practice.enabled {
+        return;
+    }
+
+    let run = Run {
+        score: score.0[0],
+        length: stats.length,
+        time: stats.time as u32,
+        date: high_score::now(),
+        difficulty: *difficulty,
+        level: level.number,
+        name: String::new(),
+        won: stats.won,
+        death: stats.death,
+    };
+    match (daily.enabled, daily.scored) {
+        _ if zen.enabled => zen.latest = high_scores.submit_zen(run),
+        (false, _) => high_scores.submit(run),
+        (true, true) => high_scores.submit_daily(run),
+        // Later attempts on the same day are only for practice
+        (true, false) => high_scores.latest = None,
+    }
+    // Keep the recording that is being played back rather than saving a copy of it, and
+    // don't count the same run twice
+    if replay.0.is_none() {
+        if !stats.resumed && (custom.0.is_none() || daily.enabled) && !zen.enabled {
+            replay::save(&recording);
+        }
+        totals.games += 1;
+        totals.food += stats.food;
+        totals.turns += stats.turns;
+        totals.distance += stats.distance;
+        totals.time += stats.time as u32;
+        if high_scores.latest == Some(0) && !stats.resumed {
+            ghost.path = stats.path.clone();
+        }
+        if high_scores.latest.is_some() {
+            state.push(GameState::TextInput).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use food::{
+        make_food, Food, FoodCount, FoodEaten, FoodTarget, FoodTimer, PowerUp, PowerUpKind,
+    };
+    use headless::{headless_app, step, tick};
+    use input::Demo;
+    use render::SnakeGlyphs;
+    use snake::{
+        Body, Bonk, Critter, CritterKind, GridPos, Grow, Player, Portal, SnakeDied, Steering,
+        TickAdvanced, Wall,
+    };
+    use std::collections::{HashSet, VecDeque};
+    use std::time::Duration;
+    use ui::{NameEntry, MAX_NAME};
+
+    // Follows a cycle through every cell of the stage: serpentine columns with
+    // the top row used as the lane back to the left edge
+    fn autopilot(pos: IVec2) -> KeyCode {
+        let half_stage = STAGE_SIZE / 2;
+        let (min, max) = (1 - half_stage, half_stage);
+        let going_up = (pos.x - min.x) % 2 == 1;
+        if pos.y == max.y {
+            if pos.x == min.x {
+                KeyCode::S
+            } else {
+                KeyCode::A
+            }
+        } else if going_up {
+            if pos.y == max.y - 1 && pos.x != max.x {
+                KeyCode::D
+            } else {
+                KeyCode::W
+            }
+        } else if pos.y == min.y {
+            KeyCode::D
+        } else {
+            KeyCode::S
+        }
+    }
+
+    #[test]
+    fn food_avoids_pending_growth() {
+        let mut world = World::new();
+        world.insert_resource(GameSettings {
+            poison_chance: 0.,
+            ..default()
+        });
+        world.init_resource::<FoodTarget>();
+        world.init_resource::<ShrinkingBounds>();
+        world.init_resource::<GameRng>();
+        world
+            .spawn()
+            .insert(Body(VecDeque::from(vec![IVec2::ZERO])));
+
+        // Queue growth on every in-bounds cell of the bottom half of the stage
+        let half = STAGE_SIZE / 2;
+        let mut pending = Vec::new();
+        for y in -half.y..0 {
+            for x in -half.x..=half.x {
+                let pos = IVec2::new(x, y);
+                if world.resource::<GameSettings>().in_bounds(pos) {
+                    pending.push(pos);
+                    world.spawn().insert(Grow {
+                        turns: 100,
+                        pos,
+                        player: 0,
+                    });
+                }
+            }
+        }
+
+        let mut stage = SystemStage::single(make_food);
+        for _ in 0..200 {
+            stage.run(&mut world);
+            let mut q_food = world.query::<(Entity, &Food)>();
+            let (entity, food) = q_food.single(&world);
+            assert!(!pending.contains(&food.pos));
+            assert_ne!(food.pos, IVec2::ZERO);
+            world.despawn(entity);
+        }
+    }
+
+    #[test]
+    fn multiple_food_targets() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(5));
+        app.update();
+        app.update();
+
+        let mut q_food = app.world.query::<&Food>();
+        let food: HashSet<_> = q_food.iter(&app.world).map(|food| food.pos).collect();
+        assert_eq!(food.len(), 5);
+        assert!(!food.contains(&IVec2::ZERO));
+    }
+
+    #[test]
+    fn eat_two_foods_in_one_tick() {
+        let mut app = headless_app();
+        app.update();
+
+        // Stack both foods right in front of the head
+        for _ in 0..2 {
+            app.world.spawn().insert(Food {
+                pos: IVec2::Y,
+                value: 1,
+                ttl: None,
+                poison: false,
+            });
+        }
+        step(&mut app, KeyCode::W);
+
+        assert_eq!(app.world.resource::<FoodCount>().0, [2, 0]);
+        let mut q_grow = app.world.query::<&Grow>();
+        let mut turns: Vec<_> = q_grow.iter(&app.world).map(|grow| grow.turns).collect();
+        turns.sort_unstable();
+        assert_eq!(turns, [1, 2]);
+    }
+
+    #[test]
+    fn eating_and_moving_send_events() {
+        let mut app = headless_app();
+        app.update();
+
+        app.world.spawn().insert(Food {
+            pos: IVec2::Y,
+            value: 1,
+            ttl: None,
+            poison: true,
+        });
+        step(&mut app, KeyCode::W);
+
+        let eaten = app.world.resource::<Events<FoodEaten>>();
+        let spoiled: Vec<_> = eaten.get_reader().iter(eaten).map(|f| f.spoiled).collect();
+        assert_eq!(spoiled, [true]);
+        assert_eq!(app.world.resource::<Score>().0, [0, 0]);
+        let ticks = app.world.resource::<Events<TickAdvanced>>();
+        let ticks: Vec<_> = ticks.get_reader().iter(ticks).map(|t| t.tick).collect();
+        assert_eq!(ticks, [1]);
+    }
+
+    #[test]
+    fn crashes_say_what_was_hit() {
+        let mut app = headless_app();
+        app.update();
+
+        app.world.spawn().insert(Wall { pos: IVec2::Y });
+        step(&mut app, KeyCode::W);
+
+        let died = app.world.resource::<Events<SnakeDied>>();
+        let causes: Vec<_> = died.get_reader().iter(died).map(|d| d.cause).collect();
+        assert_eq!(causes, [DeathCause::Wall]);
+        assert_eq!(
+            app.world.resource::<RunStats>().death,
+            Some(DeathCause::Wall)
+        );
+    }
+
+    #[test]
+    fn zen_snakes_bonk_instead_of_crashing() {
+        let mut app = headless_app();
+        app.insert_resource(GameMode::Zen);
+        app.update();
+
+        app.world.spawn().insert(Wall { pos: IVec2::Y });
+        step(&mut app, KeyCode::W);
+
+        assert!(app.world.resource::<Events<SnakeDied>>().is_empty());
+        let bonked = app.world.resource::<Events<Bonk>>();
+        let bonks: Vec<_> = bonked.get_reader().iter(bonked).map(|b| b.pos).collect();
+        assert_eq!(bonks, [IVec2::Y]);
+        assert_eq!(app.world.query::<&Body>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn custom_levels_place_walls_and_spawns() {
+        let level = editor::LevelFile {
+            walls: vec![[3, 4], [5, 5], [100, 0]],
+            portals: vec![[[-6, 2], [6, -2]]],
+            spawns: vec![[5, 5]],
+        };
+        let mut app = headless_app();
+        app.insert_resource(CustomLevel(Some(("test".to_string(), level))));
+        app.update();
+
+        // Walls off the stage or on a spawn point are left out
+        let mut q_wall = app.world.query::<&Wall>();
+        let walls: Vec<_> = q_wall.iter(&app.world).map(|wall| wall.pos).collect();
+        assert_eq!(walls, [IVec2::new(3, 4)]);
+        let mut q_portal = app.world.query::<&Portal>();
+        assert_eq!(q_portal.iter(&app.world).count(), 2);
+        let mut q_pos = app.world.query::<&GridPos>();
+        assert_eq!(q_pos.single(&app.world).0, IVec2::new(5, 5));
+    }
+
+    #[test]
+    fn custom_spawns_off_the_stage_fall_back() {
+        // The first spawn is on the second snake's column, the second one is off the stage
+        let level = editor::LevelFile {
+            walls: vec![[-1, 0]],
+            portals: vec![],
+            spawns: vec![[1, 0], [100, 0]],
+        };
+        let mut app = headless_app();
+        app.insert_resource(Players::Two);
+        app.insert_resource(CustomLevel(Some(("test".to_string(), level))));
+        app.update();
+
+        let mut q_snake = app.world.query::<(&Player, &GridPos)>();
+        let mut starts: Vec<_> = q_snake
+            .iter(&app.world)
+            .map(|(p, pos)| (p.0, pos.0))
+            .collect();
+        starts.sort_by_key(|(player, _)| *player);
+        assert_eq!(starts, [(0, IVec2::new(1, 0)), (1, IVec2::new(-1, 0))]);
+        let mut q_wall = app.world.query::<&Wall>();
+        assert_eq!(q_wall.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn daily_games_match_for_the_day() {
+        let play = || {
+            let mut app = headless_app();
+            app.insert_resource(GameMode::Daily);
+            app.update();
+            let mut q_food = app.world.query::<&Food>();
+            let food: Vec<_> = q_food.iter(&app.world).map(|food| food.pos).collect();
+            (app.world.resource::<Recording>().seed, food)
+        };
+        let (seed, food) = play();
+        assert_eq!(seed, daily::today());
+        assert!(!food.is_empty());
+        assert_eq!(play(), (seed, food));
+    }
+
+    #[test]
+    fn combos_build_and_decay() {
+        let settings = GameSettings::default();
+        let mut combo = combo::Combo::default();
+        assert_eq!(combo.eat(0, &settings), 1);
+        assert_eq!(combo.eat(0, &settings), 2);
+        assert_eq!(combo.describe(0, 12), "12 x2");
+        // The other snake's combo is its own
+        assert_eq!(combo.eat(1, &settings), 1);
+
+        for _ in 0..COMBO_TICKS {
+            combo.tick(&settings);
+        }
+        assert_eq!(combo.multiplier, [1, 1]);
+        assert_eq!(combo.eat(0, &settings), 1);
+        for _ in 0..MAX_COMBO + 2 {
+            combo.eat(0, &settings);
+        }
+        assert_eq!(combo.multiplier[0], MAX_COMBO);
+    }
+
+    #[test]
+    fn replay_matches_recording() {
+        // Follow the autopilot for a while to eat some food, then run into the wall
+        let play = |app: &mut App, steer: bool| {
+            app.update();
+            let mut states = Vec::new();
+            for i in 0..4000 {
+                let mut q_snake = app.world.query::<(&GridPos, &Body)>();
+                let (pos, body) = match q_snake.get_single(&app.world) {
+                    Ok((pos, body)) => (pos.0, body.0.len()),
+                    Err(_) => break,
+                };
+                states.push((pos, body));
+                let key = match steer && i < 2000 {
+                    true => autopilot(pos),
+                    false => KeyCode::Space,
+                };
+                step(app, key);
+            }
+            states
+        };
+
+        // Easy has no critters to get in the autopilot's way
+        let mut app = headless_app();
+        app.insert_resource(Difficulty::Easy);
+        let played = play(&mut app, true);
+        let recording = app.world.resource::<Recording>().clone();
+        assert!(played.last().unwrap().1 > 1);
+
+        let mut app = headless_app();
+        app.insert_resource(Difficulty::Easy);
+        app.insert_resource(Replay(Some(recording)));
+        let replayed = play(&mut app, false);
+        assert_eq!(played, replayed);
+    }
+
+    #[test]
+    fn replay_keeps_stage_size_and_speed_floor() {
+        let mut recording = Recording::new(7, false, Layout::Random, Difficulty::Normal);
+        recording.stage_size = StageSize::Small;
+        recording.speed_floor = 0.6;
+        let recording = Recording::from_text(&recording.to_text()).unwrap();
+
+        let mut app = headless_app();
+        app.insert_resource(Replay(Some(recording)));
+        app.update();
+
+        let small = STAGE_SIZE + StageSize::Small.extra_space();
+        assert_eq!(app.world.resource::<GameSettings>().stage_size, small);
+        assert_eq!(*app.world.resource::<StageSize>(), StageSize::Small);
+        let mut q_steering = app.world.query::<&Steering>();
+        let speed = q_steering.single(&app.world).speed;
+        assert_eq!(speed, START_SPEED * 0.6);
+    }
+
+    #[test]
+    fn fill_the_board() {
+        // Easy has no critters to get in the autopilot's way
+        let mut app = headless_app();
+        app.insert_resource(Difficulty::Easy);
+        app.update();
+
+        let cells = (STAGE_SIZE.x * STAGE_SIZE.y) as usize;
+        let mut last_food = None;
+        for _ in 0..cells * 100 {
+            let mut q_snake = app.world.query::<&GridPos>();
+            let pos = match q_snake.get_single(&app.world) {
+                Ok(pos) => pos.0,
+                Err(_) => break,
+            };
+            step(&mut app, autopilot(pos));
+
+            let mut q_food = app.world.query::<&Food>();
+            let food: Vec<_> = q_food.iter(&app.world).map(|food| food.pos).collect();
+            let mut q_body = app.world.query::<&Body>();
+            let body = match q_body.get_single(&app.world) {
+                Ok(body) => &body.0,
+                Err(_) => break,
+            };
+
+            // Overlapping growth stacks the tail on one cell, so count distinct cells
+            if food.first() != last_food.as_ref() || body.len() >= cells {
+                let occupied: HashSet<_> = body.iter().collect();
+                assert!(occupied.len() + food.len() <= cells);
+                assert!(food
+                    .iter()
+                    .all(|pos| GameSettings::default().in_bounds(*pos) && !occupied.contains(pos)));
+                last_food = food.first().copied();
+            }
+        }
+        // The snake is taken off as soon as it covers the last cell
+        assert!(app.world.resource::<RunStats>().won);
+        let state = app.world.resource::<State<GameState>>();
+        assert_eq!(state.current(), &GameState::GameOver);
+    }
+
+    #[test]
+    fn held_key_turns_once() {
+        let mut app = headless_app();
+        app.update();
+
+        let turned_at = |app: &mut App| {
+            let mut q_steering = app.world.query::<&Steering>();
+            q_steering.single(&app.world).turned_at
+        };
+
+        // A chattering key re-presses D every few milliseconds
+        tick(&mut app, &[KeyCode::D], Duration::from_millis(5));
+        let first = turned_at(&mut app);
+        for _ in 0..4 {
+            tick(&mut app, &[KeyCode::D], Duration::from_millis(5));
+            assert_eq!(turned_at(&mut app), first);
+        }
+
+        // A deliberate press after the window is committed again
+        tick(&mut app, &[KeyCode::D], Duration::from_millis(100));
+        assert!(turned_at(&mut app) > first);
+    }
+
+    #[test]
+    fn countdown_buffers_first_turn() {
+        let mut app = headless_app();
+        app.world.resource_mut::<GameSettings>().countdown = 1.0;
+        app.update();
+
+        let head = |app: &mut App| {
+            let mut q_pos = app.world.query::<&GridPos>();
+            q_pos.single(&app.world).0
+        };
+
+        tick(&mut app, &[KeyCode::D], Duration::from_millis(500));
+        tick(&mut app, &[], Duration::from_millis(400));
+        assert_eq!(head(&mut app), IVec2::ZERO);
+
+        // The turn pressed while waiting is taken on the very first move
+        tick(&mut app, &[], Duration::from_millis(300));
+        assert_eq!(head(&mut app), IVec2::X);
+    }
+
+    #[test]
+    fn practice_steps_and_rewinds() {
+        let mut app = headless_app();
+        app.update();
+        app.world.resource_mut::<Practice>().enabled = true;
+
+        let head = |app: &mut App| {
+            let mut q_pos = app.world.query::<&GridPos>();
+            q_pos.single(&app.world).0
+        };
+
+        tick(&mut app, &[], Duration::from_secs(5));
+        assert_eq!(head(&mut app), IVec2::ZERO);
+
+        step(&mut app, KeyCode::D);
+        step(&mut app, KeyCode::W);
+        assert_eq!(head(&mut app), IVec2::new(1, 1));
+
+        tick(&mut app, &[KeyCode::Back], Duration::ZERO);
+        assert_eq!(head(&mut app), IVec2::X);
+        tick(&mut app, &[KeyCode::Back], Duration::ZERO);
+        assert_eq!(head(&mut app), IVec2::ZERO);
+    }
+
+    #[test]
+    fn speed_floor_slows_the_start() {
+        let mut app = headless_app();
+        app.insert_resource(Accessibility {
+            speed_floor: 0.5,
+            ..default()
+        });
+        app.update();
+
+        let mut q_steering = app.world.query::<&Steering>();
+        let speed = q_steering.single(&app.world).speed;
+        assert_eq!(speed, START_SPEED * 0.5);
+    }
+
+    #[test]
+    fn boost_moves_faster_and_costs_tail() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+
+        let mut q_body = app.world.query::<&mut Body>();
+        q_body.single_mut(&mut app.world).0 = (0..10).map(|y| IVec2::new(0, -y)).collect();
+
+        // Twice the normal speed covers a cell every frame
+        let frame = Duration::from_secs_f32(1.0 / (START_SPEED * BOOST_FACTOR));
+        for _ in 0..BOOST_SHRINK_TICKS * 2 {
+            tick(&mut app, &[KeyCode::LShift], frame);
+        }
+
+        let mut q_snake = app.world.query::<(&GridPos, &Body)>();
+        let (pos, body) = q_snake.single(&app.world);
+        assert_eq!(pos.0, IVec2::new(0, BOOST_SHRINK_TICKS as i32 * 2));
+        // The second lost segment is still pending
+        assert_eq!(body.0.len(), 9);
+    }
+
+    #[test]
+    fn fast_turns_are_queued() {
+        let mut app = headless_app();
+        app.update();
+
+        let head = |app: &mut App| {
+            let mut q_pos = app.world.query::<&GridPos>();
+            q_pos.single(&app.world).0
+        };
+
+        // Right then up again, both well within a single tick
+        tick(&mut app, &[KeyCode::D], Duration::from_millis(10));
+        tick(&mut app, &[KeyCode::W], Duration::from_millis(10));
+        assert_eq!(head(&mut app), IVec2::ZERO);
+
+        tick(&mut app, &[], Duration::from_secs(1));
+        assert_eq!(head(&mut app), IVec2::new(1, 0));
+        tick(&mut app, &[], Duration::from_secs(1));
+        assert_eq!(head(&mut app), IVec2::new(1, 1));
+    }
+
+    #[test]
+    fn quick_turn_and_reverse_survives() {
+        let mut app = headless_app();
+        app.update();
+
+        // Three segments heading up from the origin
+        let mut q_snake = app.world.query::<(&mut Body, &mut Steering)>();
+        let (mut body, mut steering) = q_snake.single_mut(&mut app.world);
+        body.0 = VecDeque::from(vec![IVec2::ZERO, -IVec2::Y, -IVec2::Y * 2]);
+        steering.prev = -IVec2::Y;
+
+        // Right then down within one tick, down must not be applied straight into the neck
+        tick(&mut app, &[KeyCode::D], Duration::from_millis(10));
+        tick(&mut app, &[KeyCode::S], Duration::from_millis(10));
+        tick(&mut app, &[], Duration::from_secs(1));
+        tick(&mut app, &[], Duration::from_secs(1));
+
+        let mut q_pos = app.world.query::<&GridPos>();
+        let head = q_pos.get_single(&app.world).expect("snake died").0;
+        assert_eq!(head, IVec2::new(1, -1));
+    }
+
+    #[test]
+    fn head_on_collision_is_a_draw() {
+        let mut app = headless_app();
+        app.insert_resource(Players::Two);
+        app.update();
+
+        // The snakes start two cells apart and turn into each other
+        tick(
+            &mut app,
+            &[KeyCode::D, KeyCode::Left],
+            Duration::from_secs(1),
+        );
+
+        assert_eq!(app.world.query::<&Body>().iter(&app.world).count(), 0);
+        assert_eq!(app.world.resource::<Winner>().0, None);
+    }
+
+    #[test]
+    fn running_into_the_other_snake_loses() {
+        let mut app = headless_app();
+        app.insert_resource(Players::Two);
+        app.update();
+
+        let mut q_snake = app.world.query::<(&mut Body, &Player)>();
+        for (mut body, player) in q_snake.iter_mut(&mut app.world) {
+            if player.0 == 1 {
+                body.0 = (0..4).map(|y| IVec2::new(1, -y)).collect();
+            }
+        }
+
+        // The first snake turns into the second one's body on its second move
+        step(&mut app, KeyCode::D);
+        assert_eq!(app.world.query::<&Body>().iter(&app.world).count(), 2);
+        step(&mut app, KeyCode::D);
+
+        assert_eq!(app.world.query::<&Body>().iter(&app.world).count(), 0);
+        assert_eq!(app.world.resource::<Winner>().0, Some(1));
+    }
+
+    #[test]
+    fn computer_snake_goes_for_food() {
+        let mut app = headless_app();
+        app.insert_resource(Players::Computer);
+        app.insert_resource(FoodTarget(0));
+        app.update();
+
+        // Seven moves away from where the computer snake starts, in reach on Normal
+        app.world.spawn().insert(Food {
+            pos: IVec2::new(5, 3),
+            value: 1,
+            ttl: None,
+            poison: false,
+        });
+        for _ in 0..7 {
+            tick(&mut app, &[], Duration::from_secs(1));
+        }
+
+        assert_eq!(app.world.resource::<Score>().0, [0, 1]);
+    }
+
+    #[test]
+    fn ghost_passes_through_own_body() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+
+        // Heading up with the body curled around to the right, the ghost just ahead
+        let mut q_snake = app.world.query::<(&mut Body, &mut Steering)>();
+        let (mut body, mut steering) = q_snake.single_mut(&mut app.world);
+        body.0 = [
+            [0, 0],
+            [0, -1],
+            [0, -2],
+            [1, -2],
+            [1, -1],
+            [1, 0],
+            [1, 1],
+            [1, 2],
+            [1, 3],
+        ]
+        .into_iter()
+        .map(IVec2::from)
+        .collect();
+        steering.prev = -IVec2::Y;
+        app.world.spawn().insert(PowerUp {
+            pos: IVec2::Y,
+            kind: PowerUpKind::Ghost,
+            ttl: 10,
+        });
+
+        step(&mut app, KeyCode::W);
+        step(&mut app, KeyCode::D);
+
+        let mut q_pos = app.world.query::<&GridPos>();
+        let head = q_pos.get_single(&app.world).expect("snake died").0;
+        assert_eq!(head, IVec2::new(1, 1));
+    }
+
+    #[test]
+    fn portal_comes_out_of_its_twin() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+        let (a, b) = (IVec2::new(0, 2), IVec2::new(5, -3));
+        app.world.spawn().insert(Portal { pos: a, exit: b });
+        app.world.spawn().insert(Portal { pos: b, exit: a });
+
+        step(&mut app, KeyCode::W);
+        step(&mut app, KeyCode::W);
+
+        let mut q_pos = app.world.query::<&GridPos>();
+        let head = q_pos.get_single(&app.world).expect("snake died").0;
+        assert_eq!(head, IVec2::new(5, -2));
+    }
+
+    #[test]
+    fn running_into_a_critter_ends_the_game() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+        app.world.spawn().insert(Critter {
+            pos: IVec2::new(0, 2),
+            kind: CritterKind::Spider,
+            dir: IVec2::X,
+        });
+
+        step(&mut app, KeyCode::W);
+        step(&mut app, KeyCode::W);
+
+        let state = app.world.resource::<State<GameState>>();
+        assert_eq!(state.current(), &GameState::GameOver);
+    }
+
+    #[test]
+    fn eating_enough_food_moves_to_the_next_level() {
+        let mut app = headless_app();
+        app.world.resource_mut::<GameSettings>().level_food = 1;
+        app.update();
+
+        app.world.spawn().insert(Food {
+            pos: IVec2::new(0, 2),
+            value: 1,
+            ttl: None,
+            poison: false,
+        });
+        step(&mut app, KeyCode::W);
+        step(&mut app, KeyCode::W);
+
+        assert_eq!(app.world.resource::<Level>().number, 2);
+        assert_eq!(
+            app.world.resource::<State<GameState>>().current(),
+            &GameState::LevelUp
+        );
+        let mut q_snake = app.world.query::<(&GridPos, &Steering)>();
+        let (pos, steering) = q_snake.single(&app.world);
+        assert_eq!(pos.0, IVec2::ZERO);
+        assert_eq!(steering.speed, START_SPEED + LEVEL_SPEED);
+        // Food from the first level is gone and the next layout is up
+        assert_eq!(app.world.query::<&Food>().iter(&app.world).count(), 0);
+        assert!(app.world.query::<&Wall>().iter(&app.world).count() > 0);
+    }
+
+    #[test]
+    fn rotten_food_shrinks_the_snake() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+
+        let mut q_body = app.world.query::<&mut Body>();
+        q_body.single_mut(&mut app.world).0 = [[0, 0], [0, -1], [0, -2]]
+            .into_iter()
+            .map(IVec2::from)
+            .collect();
+        app.world
+            .spawn()
+            .insert(Food {
+                pos: IVec2::new(0, 2),
+                value: 1,
+                ttl: None,
+                poison: false,
+            })
+            .insert(FoodTimer(1));
+        // Goes off on the first move, gets eaten on the second and the tail comes off on
+        // the third
+        for _ in 0..3 {
+            step(&mut app, KeyCode::W);
+        }
+
+        assert_eq!(app.world.resource::<FoodCount>().0, [0, 0]);
+        assert_eq!(app.world.resource::<Score>().0, [0, 0]);
+        assert_eq!(q_body.single(&app.world).0.len(), 2);
+    }
+
+    #[test]
+    fn food_eaten_as_it_rots_is_still_fresh() {
+        let mut app = headless_app();
+        app.insert_resource(FoodTarget(0));
+        app.update();
+
+        app.world
+            .spawn()
+            .insert(Food {
+                pos: IVec2::Y,
+                value: 1,
+                ttl: None,
+                poison: false,
+            })
+            .insert(FoodTimer(1));
+        step(&mut app, KeyCode::W);
+
+        assert_eq!(app.world.resource::<FoodCount>().0, [1, 0]);
+        assert_eq!(app.world.query::<&Food>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn snake_glyphs_follow_the_body() {
+        let glyphs = SnakeGlyphs::double_line();
+        // Head at the top, down and around a corner to the tail on the right
+        let body: VecDeque<_> = [[0, 1], [0, 0], [1, 0], [2, 0]]
+            .into_iter()
+            .map(IVec2::from)
+            .collect();
+        let pieces: Vec<_> = (1..body.len())
+            .map(|i| glyphs.segment(&body, i).unwrap())
+            .collect();
+        assert_eq!(pieces, ['╚', '═', '─']);
+        assert_eq!(glyphs.head(IVec2::Y), Some('▲'));
+        assert_eq!(glyphs.head(IVec2::ZERO), None);
+    }
+
+    #[test]
+    fn shrinking_arena_closes_from_the_edge() {
+        let settings = GameSettings::default();
+        let mut bounds = ShrinkingBounds::default();
+        let edge = settings.stage_size.x / 2;
+        assert!(bounds.is_safe(IVec2::new(edge, 0), &settings));
+
+        bounds.rings = 1;
+        assert!(!bounds.is_safe(IVec2::new(edge, 0), &settings));
+        assert!(!bounds.is_safe(IVec2::new(1 - edge, 0), &settings));
+        assert!(bounds.is_safe(IVec2::new(edge - 1, 2 - edge), &settings));
+
+        bounds.rings = edge - MIN_ARENA;
+        assert!(!bounds.can_shrink(&settings));
+    }
+
+    #[test]
+    fn demo_snake_heads_for_food() {
+        let size = IVec2::new(12, 12);
+        let mut demo = Demo::default();
+        demo.start(size);
+        let food = demo.body[0] + IVec2::new(0, 2);
+        demo.food = food;
+
+        demo.step(size);
+        demo.step(size);
+        assert_eq!(demo.body[0], food);
+        assert_eq!(demo.body.len(), 4);
+        assert_ne!(demo.food, food);
+    }
+
+    #[test]
+    fn name_entry_edits_under_the_cursor() {
+        let mut entry = NameEntry::default();
+        for c in "ab-c".chars() {
+            entry.type_char(c);
+        }
+        assert_eq!(entry.name, ['A', 'B', 'C']);
+        assert!(entry.is_valid());
+
+        entry.move_cursor(-1);
+        entry.cycle(1);
+        entry.backspace();
+        assert_eq!(entry.name, ['A', 'D']);
+        assert!(!entry.is_valid());
+
+        for c in "0123456789".chars() {
+            entry.type_char(c);
+        }
+        assert_eq!(entry.name.len(), MAX_NAME);
+        assert_eq!(entry.name[..2], ['A', '0']);
+    }
+
+    #[test]
+    fn picks_come_back_after_the_game() {
+        let mut world = World::new();
+        world.init_resource::<Picks>();
+        world.init_resource::<GameSettings>();
+        world.init_resource::<Difficulty>();
+        world.init_resource::<StageSize>();
+        world.init_resource::<Layout>();
+        world.insert_resource(WallMode::Solid);
+        let mut keep = SystemStage::single(keep_picks);
+        let mut restore = SystemStage::single(restore_picks);
+
+        // What a zen or daily game does to them once it has started
+        let swap = |world: &mut World| {
+            world.insert_resource(WallMode::Wrap);
+            world.insert_resource(Layout::Box);
+            world.resource_scope(|world, mut settings: Mut<GameSettings>| {
+                let mut difficulty = world.resource_mut::<Difficulty>();
+                set_difficulty(&mut difficulty, &mut settings, Difficulty::Easy);
+            });
+        };
+        let picked = |world: &World| {
+            assert_eq!(*world.resource::<WallMode>(), WallMode::Solid);
+            assert_eq!(*world.resource::<Layout>(), Layout::Random);
+            assert_eq!(*world.resource::<Difficulty>(), Difficulty::Normal);
+            assert_eq!(world.resource::<GameSettings>().stage_size, STAGE_SIZE);
+        };
+
+        keep.run(&mut world);
+        swap(&mut world);
+        // Playing again from the game over screen starts from the picks
+        keep.run(&mut world);
+        picked(&world);
+        swap(&mut world);
+        restore.run(&mut world);
+        picked(&world);
+        assert!(world.resource::<Picks>().0.is_none());
+    }
+
+    #[test]
+    fn game_over_after_a_small_stage_fits_the_table() {
+        let mut app = App::new();
+        let run = Run {
+            score: 10,
+            length: 5,
+            time: 60,
+            date: 0,
+            difficulty: Difficulty::Normal,
+            level: 1,
+            name: "AAA".into(),
+            won: false,
+            death: Some(DeathCause::Wall),
+        };
+        app.insert_resource(HighScores {
+            runs: vec![run; 10],
+            latest: Some(9),
+            ..default()
+        })
+        .init_resource::<Score>()
+        .init_resource::<RunStats>()
+        .init_resource::<Recording>()
+        .init_resource::<Players>()
+        .init_resource::<Winner>()
+        .init_resource::<WallMode>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<Theme>()
+        .init_resource::<Level>()
+        .init_resource::<Totals>()
+        .init_resource::<Localization>()
+        .init_resource::<crash::Wreck>()
+        .init_resource::<Daily>()
+        .init_resource::<Zen>()
+        .add_system(crash::freeze_board)
+        .add_system(render::fit_menu.after(crash::freeze_board))
+        .add_system(ui::render_game_over.after(render::fit_menu));
+        // Where a Small stage left it
+        let small = STAGE_SIZE + StageSize::Small.extra_space() + 2;
+        let mut term = Terminal::with_size(small.as_uvec2());
+        term.put_char([30, 30], 'X');
+        app.world.spawn().insert(term);
+        app.update();
+
+        let mut q_term = app.world.query::<&Terminal>();
+        let term = q_term.single(&app.world);
+        assert_eq!(term.size(), (STAGE_SIZE + 2).as_uvec2());
+        // The board is kept in the middle
+        assert_eq!(term.get_char([35, 35]), 'X');
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+// Built in rather than loaded through the asset server so the text is there from the first
+// frame, on the web as well
+const FILES: [&str; 4] = [
+    include_str!("../assets/lang/en.toml"),
+    include_str!("../assets/lang/de.toml"),
+    include_str!("../assets/lang/fr.toml"),
+    include_str!("../assets/lang/ja.toml"),
+];
+
+// Picked on the options screen, in the same order as `FILES`
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Japanese,
+    ];
+
+    pub fn index(self) -> usize {
+        Language::ALL.iter().position(|l| *l == self).unwrap()
+    }
+
+    pub fn next(self) -> Self {
+        Language::ALL[(self.index() + 1) % Language::ALL.len()]
+    }
+}
+
+// All the text drawn on the terminal, keyed by "section.name" after the tables in the
+// language files
+pub struct Localization {
+    pub language: Language,
+    tables: Vec<HashMap<String, String>>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        let tables = FILES
+            .iter()
+            .map(|file| {
+                let mut table = HashMap::new();
+                if let Ok(value) = file.parse::<Value>() {
+                    flatten("", value, &mut table);
+                }
+                table
+            })
+            .collect();
+        Self {
+            language: Language::default(),
+            tables,
+        }
+    }
+}
+
+fn flatten(prefix: &str, value: Value, table: &mut HashMap<String, String>) {
+    match value {
+        Value::Table(entries) => {
+            for (key, value) in entries {
+                let key = match prefix.is_empty() {
+                    true => key,
+                    false => format!("{}.{}", prefix, key),
+                };
+                flatten(&key, value, table);
+            }
+        }
+        Value::String(text) => {
+            table.insert(prefix.to_string(), text);
+        }
+        _ => {}
+    }
+}
+
+impl Localization {
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            ..Self::default()
+        }
+    }
+
+    // The picked language first and then English, which has everything
+    fn lookup(&self, key: &str) -> Option<&str> {
+        [self.language, Language::English]
+            .iter()
+            .find_map(|language| self.tables[language.index()].get(key))
+            .map(String::as_str)
+    }
+
+    // Text missing from every file shows its key, so it's easy to spot
+    pub fn get(&self, key: &str) -> String {
+        self.lookup(key).unwrap_or(key).to_string()
+    }
+
+    // `get` with each `{}` replaced by the next of `args`, or `{0}`, `{1}`... by that one for
+    // languages that put them in another order
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let text = self.lookup(key).unwrap_or(key);
+        let mut out = String::new();
+        let mut next = 0;
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            out.push_str(&rest[..start]);
+            let i = match rest[start + 1..end].parse() {
+                Ok(i) => i,
+                Err(_) => {
+                    next += 1;
+                    next - 1
+                }
+            };
+            if let Some(arg) = args.get(i) {
+                out.push_str(&arg.to_string());
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    // Names of enum values are looked up under `section` by their debug name, and shown as
+    // that if there's no text for them
+    pub fn name<T: Debug>(&self, section: &str, value: T) -> String {
+        let name = format!("{:?}", value);
+        let key = format!("{}.{}", section, name);
+        self.lookup(&key).map_or(name, str::to_string)
+    }
+
+    pub fn on_off(&self, on: bool) -> String {
+        match on {
+            true => self.get("common.on"),
+            false => self.get("common.off"),
+        }
+    }
+}
+
+// Cells `text` takes up. Every glyph is one cell on the terminal, so it's the number of
+// chars rather than the bytes accented letters take more of
+pub fn width(text: &str) -> i32 {
+    text.chars().count() as i32
+}
+
+// Where `text` starts to be centered on the terminal
+pub fn centered(text: &str) -> i32 {
+    -width(text) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Difficulty;
+
+    #[test]
+    fn text_falls_back_to_english() {
+        let mut loc = Localization::new(Language::German);
+        assert_eq!(loc.format("hud.score", &[&12, &3]), "Punkte: 12  Level: 3");
+        assert_eq!(loc.name("language", Language::French), "Français");
+        assert_eq!(loc.get("no.such.text"), "no.such.text");
+
+        loc.language = Language::French;
+        assert_eq!(loc.on_off(false), "Non");
+        assert_eq!(loc.name("difficulty", Difficulty::Hard), "Difficile");
+        assert_eq!(width("Français"), 8);
+    }
+}
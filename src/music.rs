@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use bevy::prelude::{App, AssetServer, Assets, Handle, Local, Plugin, Res, ResMut, State};
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioInstance, AudioTween};
+
+use crate::volume::AudioSettings;
+use crate::GameState;
+
+const CROSSFADE_TIME: f32 = 1.5;
+const VOLUME_FADE_TIME: f32 = 0.2;
+
+// Channel the music plays on, apart from the sound effects so it has a volume of its own
+pub struct Music;
+
+// Both tracks loop for as long as the game runs, switching only changes which one is heard
+#[derive(Default)]
+pub struct MusicSinks {
+    pub menu: Handle<AudioInstance>,
+    pub game: Handle<AudioInstance>,
+}
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<Music>()
+            .init_resource::<MusicSinks>()
+            .add_startup_system(start_music)
+            .add_system(crossfade);
+    }
+}
+
+fn start_music(
+    server: Res<AssetServer>,
+    channel: Res<AudioChannel<Music>>,
+    mut sinks: ResMut<MusicSinks>,
+) {
+    sinks.menu = channel
+        .play(server.load("menu.wav"))
+        .looped()
+        .with_volume(0.0)
+        .handle();
+    sinks.game = channel
+        .play(server.load("game.wav"))
+        .looped()
+        .with_volume(0.0)
+        .handle();
+}
+
+// The game track plays while a game is on, paused or between levels included, and the
+// menu track everywhere else. Switching fades one into the other, mute and volume
+// changes follow almost right away
+fn crossfade(
+    state: Res<State<GameState>>,
+    settings: Res<AudioSettings>,
+    sinks: Res<MusicSinks>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    mut applied: Local<Option<(bool, f64)>>,
+) {
+    let in_game = matches!(
+        state.current(),
+        GameState::Playing | GameState::Paused | GameState::LevelUp
+    );
+    let volume = settings.music_gain();
+    if *applied == Some((in_game, volume)) {
+        return;
+    }
+
+    let fade = match *applied {
+        Some((was_in_game, _)) if was_in_game == in_game => VOLUME_FADE_TIME,
+        _ => CROSSFADE_TIME,
+    };
+    let tween = || AudioTween::linear(Duration::from_secs_f32(fade));
+    let (heard, silent) = match in_game {
+        true => (&sinks.game, &sinks.menu),
+        false => (&sinks.menu, &sinks.game),
+    };
+    // Instances only exist once the tracks have loaded, try again next frame until then
+    match instances.get_mut(heard) {
+        Some(instance) => instance.set_volume(volume, tween()),
+        None => return,
+    };
+    if let Some(instance) = instances.get_mut(silent) {
+        instance.set_volume(0.0, tween());
+    }
+    *applied = Some((in_game, volume));
+}
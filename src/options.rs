@@ -0,0 +1,118 @@
+use bevy::prelude::default;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility::SPEED_FLOORS;
+use crate::juice::ScreenShake;
+use crate::locale::Language;
+use crate::render::Palette;
+use crate::touch::TouchMode;
+use crate::{Players, WallMode};
+
+const FILE_NAME: &str = "options.toml";
+
+// Choices from the options and accessibility screens, stored next to the executable.
+// Options missing from the file keep their defaults, a file that can't be read is ignored
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Options {
+    pub palette: Palette,
+    pub players: Players,
+    pub wall_mode: WallMode,
+    pub screen_shake: ScreenShake,
+    pub touch_mode: TouchMode,
+    pub show_ghost: bool,
+    pub smooth: bool,
+    pub language: Language,
+    pub shapes: bool,
+    // One of `SPEED_FLOORS`, anything else goes back to full speed
+    pub speed_floor: f32,
+    pub announce: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            palette: default(),
+            players: default(),
+            wall_mode: default(),
+            screen_shake: default(),
+            touch_mode: default(),
+            show_ghost: true,
+            smooth: false,
+            language: default(),
+            shapes: false,
+            speed_floor: SPEED_FLOORS[0],
+            announce: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut options: Options = toml::from_str(text).ok()?;
+        if !SPEED_FLOORS.contains(&options.speed_floor) {
+            options.speed_floor = SPEED_FLOORS[0];
+        }
+        Some(options)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> Options {
+    path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| Options::from_text(&text))
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(options: &Options) {
+    if let (Some(path), Ok(text)) = (path(), toml::to_string(options)) {
+        std::fs::write(path, text).ok();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path() -> Option<std::path::PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(FILE_NAME))
+}
+
+// No file system on the web, options only last for the session
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> Options {
+    Options::default()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(_options: &Options) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_round_trip() {
+        let options = Options {
+            palette: Palette::ALL[1],
+            players: Players::Two,
+            wall_mode: WallMode::Wrap,
+            screen_shake: ScreenShake(false),
+            show_ghost: false,
+            speed_floor: SPEED_FLOORS[2],
+            announce: true,
+            ..default()
+        };
+        let text = toml::to_string(&options).unwrap();
+        assert_eq!(Options::from_text(&text), Some(options));
+    }
+
+    #[test]
+    fn missing_or_bad_options_keep_their_defaults() {
+        let options = Options::from_text("players = \"Two\"\nspeed_floor = 0.3").unwrap();
+        assert_eq!(options.players, Players::Two);
+        assert_eq!(options.speed_floor, SPEED_FLOORS[0]);
+        assert!(options.show_ghost);
+        assert_eq!(Options::from_text("players = \"Three\""), None);
+    }
+}
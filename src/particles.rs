@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::{GameSettings, GameState};
+
+const GLYPHS: [char; 3] = ['·', '*', '+'];
+const EAT_COUNT: usize = 8;
+// Cells per second the particles start out at
+const EAT_SPEED: f32 = 8.0;
+const DEATH_SPEED: f32 = 5.0;
+// Share of the speed lost every second
+const DRAG: f32 = 0.9;
+const LIFETIME: f32 = 0.6;
+
+// A glyph flying across the stage, in the same cells the snake moves on
+#[derive(Component)]
+pub struct Particle {
+    pos: Vec2,
+    glyph: char,
+    color: Color,
+}
+
+#[derive(Component)]
+pub struct Velocity(Vec2);
+
+#[derive(Component)]
+pub struct Lifetime(Timer);
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(simulate)
+            .add_system_to_stage(CoreStage::PostUpdate, draw_game_over);
+    }
+}
+
+// Directions and glyphs come from `rand` rather than the game's rng so replays stay the same
+fn spawn(commands: &mut Commands, pos: IVec2, speed: f32, color: Color) {
+    let angle = rand::random::<f32>() * std::f32::consts::TAU;
+    let speed = speed * (0.5 + rand::random::<f32>() / 2.0);
+    let glyph = GLYPHS[rand::random::<usize>() % GLYPHS.len()];
+    commands
+        .spawn()
+        .insert(Particle {
+            pos: pos.as_vec2(),
+            glyph,
+            color,
+        })
+        .insert(Velocity(Vec2::from_angle(angle) * speed))
+        .insert(Lifetime(Timer::from_seconds(LIFETIME, false)));
+}
+
+// Radiating out from eaten food
+pub fn burst(commands: &mut Commands, pos: IVec2, color: Color) {
+    for _ in 0..EAT_COUNT {
+        spawn(commands, pos, EAT_SPEED, color);
+    }
+}
+
+// One from every segment of a dead snake
+pub fn scatter(commands: &mut Commands, body: impl Iterator<Item = (IVec2, Color)>) {
+    for (pos, color) in body {
+        spawn(commands, pos, DEATH_SPEED, color);
+    }
+}
+
+fn simulate(
+    time: Res<Time>,
+    mut q_particle: Query<(Entity, &mut Particle, &mut Velocity, &mut Lifetime)>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut particle, mut velocity, mut lifetime) in &mut q_particle {
+        if lifetime.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.pos += velocity.0 * dt;
+        velocity.0 *= (1.0 - DRAG * dt).max(0.0);
+    }
+}
+
+// Called by `render` before the snake goes on top, anything that left the stage is dropped
+pub fn draw(term: &mut Terminal, q_particle: &Query<&Particle>, settings: &GameSettings) {
+    for particle in q_particle {
+        let cell = particle.pos.round().as_ivec2();
+        if settings.in_bounds(cell) {
+            let pos = settings.to_term(cell);
+            term.put_char(pos, particle.glyph.fg(particle.color));
+        }
+    }
+}
+
+// `render` stops once the game ends, so the last of the particles from the crash go over
+// the game over screen and are taken off again the next frame
+fn draw_game_over(
+    state: Res<State<GameState>>,
+    settings: Res<GameSettings>,
+    q_particle: Query<&Particle>,
+    mut q_term: Query<&mut Terminal>,
+    mut covered: Local<Vec<(IVec2, Tile, char)>>,
+) {
+    let mut term = match q_term.get_single_mut() {
+        Ok(term) => term,
+        Err(_) => return,
+    };
+    // Backwards, particles sharing a cell covered each other
+    for (pos, tile, drawn) in covered.drain(..).rev() {
+        if term.is_in_bounds(pos) && term.get_char(pos) == drawn {
+            term.put_tile(pos, tile);
+        }
+    }
+    if state.current() != &GameState::GameOver {
+        return;
+    }
+    for particle in &q_particle {
+        let cell = particle.pos.round().as_ivec2();
+        let pos = settings.to_term(cell);
+        if settings.in_bounds(cell) && term.is_in_bounds(pos) {
+            covered.push((pos, *term.get_tile(pos), particle.glyph));
+            term.put_char(pos, particle.glyph.fg(particle.color));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::controls::{Action, KeyBindings};
+use crate::locale::{self, Localization};
+use crate::render::Theme;
+use crate::replay::Replay;
+use crate::suspend;
+use crate::{Countdown, GameMode, GameRng, GameState, Players};
+
+// Steps that can be taken back
+const HISTORY: usize = 100;
+const STEP_ACTIONS: [Action; 5] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::Confirm,
+];
+
+// Practice mode. The snakes stand still until a direction or confirm is pressed and then
+// move exactly one cell, Backspace puts the board back the way it was a step earlier
+#[derive(Default)]
+pub struct Practice {
+    // Set from the mode picked on the title screen when each game starts
+    pub enabled: bool,
+    // Whether the snakes move this frame
+    pub step: bool,
+    // The board before each of the last steps as `suspend` writes it, along with the rng
+    // so the same food comes down again. Newest at the back
+    history: VecDeque<(String, StdRng)>,
+}
+
+impl Practice {
+    pub fn start(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.step = false;
+        self.history.clear();
+    }
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct PracticePlugin {
+    pub headless: bool,
+}
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Practice>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_practice))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(step_practice.exclusive_system().at_start()),
+            );
+        if !self.headless {
+            app.add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(draw_practice.after(crate::render::render)),
+            );
+        }
+    }
+}
+
+// Replays play out at their own pace. Single player only, like speedruns
+fn start_practice(
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    replay: Res<Replay>,
+    mut practice: ResMut<Practice>,
+) {
+    practice.start(*mode == GameMode::Practice && *players == Players::One && replay.0.is_none());
+}
+
+// Before anything else runs, so the board that's saved is the one the step starts from
+fn step_practice(world: &mut World) {
+    world.resource_mut::<Practice>().step = false;
+    if !world.resource::<Practice>().enabled || !world.resource::<Countdown>().0.finished() {
+        return;
+    }
+    let input = world.resource::<Input<KeyCode>>();
+    let bindings = world.resource::<KeyBindings>();
+    let rewind = input.just_pressed(KeyCode::Back);
+    let step = STEP_ACTIONS
+        .iter()
+        .any(|action| bindings.keys(*action).any(|key| input.just_pressed(key)));
+
+    if rewind {
+        if let Some((text, rng)) = world.resource_mut::<Practice>().history.pop_back() {
+            suspend::load(world, &text);
+            world.resource_mut::<GameRng>().0 = rng;
+        }
+    } else if step {
+        let text = suspend::to_text(world);
+        let rng = world.resource::<GameRng>().0.clone();
+        let mut practice = world.resource_mut::<Practice>();
+        practice.history.push_back((text, rng));
+        if practice.history.len() > HISTORY {
+            practice.history.pop_front();
+        }
+        practice.step = true;
+    }
+}
+
+// Over the time in the hud, which doesn't mean much while practicing
+fn draw_practice(
+    practice: Res<Practice>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if !practice.enabled {
+        return;
+    }
+    let steps = format!("{:>3}", practice.history.len());
+    let text = format!(" {} ", loc.format("hud.steps_back", &[&steps]));
+    q_term.single_mut().put_string(
+        [locale::width(&text) + 1, 0].pivot(Pivot::TopRight),
+        text.fg(theme.colors().text),
+    );
+}
@@ -0,0 +1,630 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use bevy_ascii_terminal::ToWorld;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility::Accessibility;
+use crate::combo::Combo;
+use crate::food::{Food, FoodEaten, PowerUp, Rotten};
+use crate::high_score::{Ghost, HighScores};
+use crate::juice;
+use crate::locale::{self, Localization};
+use crate::particles::{self, Particle};
+use crate::snake::{Body, Critter, Player, Portal, SnakeDied, Steering, Wall};
+use crate::zen::Zen;
+use crate::{
+    Countdown, GameSettings, GameState, Level, Players, RunStats, Score, ShrinkingBounds, DIRS,
+};
+
+// Cells of the best run's path drawn behind the ghost's head
+const GHOST_TRAIL: usize = 6;
+
+pub struct Theme {
+    // Straight and corner pieces for the body, solid blocks are drawn if `None`
+    pub snake_glyphs: Option<SnakeGlyphs>,
+    pub palette: Palette,
+    // Each player's snake fades from the first color at the head to the second at the tail
+    pub body_colors: [[Color; 2]; 2],
+    // Half a cell is drawn ahead of the head and taken off the tail once the snake is
+    // halfway to its next step
+    pub smooth: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            snake_glyphs: Some(SnakeGlyphs::double_line()),
+            palette: Palette::default(),
+            body_colors: Palette::default().body_colors(),
+            smooth: false,
+        }
+    }
+}
+
+// Named color sets picked on the options screen
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Classic,
+    Neon,
+    Monochrome,
+    // Orange and sky blue, told apart with any kind of color blindness
+    ColorblindSafe,
+    // Pure white on black, everything is told apart by its glyph with the accessibility
+    // shapes on
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Classic,
+        Palette::Neon,
+        Palette::Monochrome,
+        Palette::ColorblindSafe,
+        Palette::HighContrast,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Palette::ALL.iter().position(|p| *p == self).unwrap();
+        Palette::ALL[(i + 1) % Palette::ALL.len()]
+    }
+
+    pub fn body_colors(self) -> [[Color; 2]; 2] {
+        match self {
+            Palette::Classic => [
+                [Color::rgb(0.6, 1.0, 0.5), Color::rgb(0.1, 0.35, 0.1)],
+                [Color::rgb(0.5, 0.8, 1.0), Color::rgb(0.1, 0.2, 0.45)],
+            ],
+            Palette::Neon => [
+                [Color::rgb(1.0, 0.2, 0.9), Color::rgb(0.3, 0.0, 0.4)],
+                [Color::rgb(0.2, 1.0, 1.0), Color::rgb(0.0, 0.25, 0.4)],
+            ],
+            Palette::Monochrome => [
+                [Color::WHITE, Color::rgb(0.3, 0.3, 0.3)],
+                [Color::rgb(0.7, 0.7, 0.7), Color::rgb(0.2, 0.2, 0.2)],
+            ],
+            Palette::ColorblindSafe => [
+                [Color::rgb(0.9, 0.6, 0.0), Color::rgb(0.35, 0.2, 0.0)],
+                [Color::rgb(0.35, 0.7, 0.9), Color::rgb(0.0, 0.2, 0.35)],
+            ],
+            Palette::HighContrast => [[Color::WHITE; 2]; 2],
+        }
+    }
+}
+
+// Everything besides the snakes that follows the palette
+#[derive(Clone, Copy)]
+pub struct Colors {
+    pub food: Color,
+    pub bonus: Color,
+    pub poison: Color,
+    pub rotten: Color,
+    pub wall: Color,
+    // Screen headings and the frames around them
+    pub title: Color,
+    pub text: Color,
+    // Selected menu rows, the latest high score and popups
+    pub highlight: Color,
+    // Hints and table headers
+    pub faded: Color,
+}
+
+impl Palette {
+    pub fn colors(self) -> Colors {
+        match self {
+            Palette::Classic => Colors {
+                food: Color::WHITE,
+                bonus: Color::YELLOW,
+                poison: Color::RED,
+                rotten: Color::rgb(0.5, 0.4, 0.1),
+                wall: Color::GRAY,
+                title: Color::BLUE,
+                text: Color::WHITE,
+                highlight: Color::YELLOW,
+                faded: Color::GRAY,
+            },
+            Palette::Neon => Colors {
+                food: Color::rgb(1.0, 1.0, 0.3),
+                bonus: Color::rgb(0.3, 1.0, 0.4),
+                poison: Color::rgb(1.0, 0.1, 0.3),
+                rotten: Color::rgb(0.5, 0.6, 0.1),
+                wall: Color::rgb(0.45, 0.25, 0.85),
+                title: Color::rgb(1.0, 0.2, 0.9),
+                text: Color::rgb(0.85, 1.0, 1.0),
+                highlight: Color::rgb(0.2, 1.0, 1.0),
+                faded: Color::rgb(0.45, 0.35, 0.6),
+            },
+            Palette::Monochrome => Colors {
+                food: Color::WHITE,
+                bonus: Color::rgb(0.85, 0.85, 0.85),
+                poison: Color::rgb(0.55, 0.55, 0.55),
+                rotten: Color::rgb(0.3, 0.3, 0.3),
+                wall: Color::rgb(0.4, 0.4, 0.4),
+                title: Color::WHITE,
+                text: Color::rgb(0.8, 0.8, 0.8),
+                highlight: Color::WHITE,
+                faded: Color::rgb(0.45, 0.45, 0.45),
+            },
+            // Reddish purple for poison so it never depends on telling red from green
+            Palette::ColorblindSafe => Colors {
+                food: Color::WHITE,
+                bonus: Color::rgb(0.95, 0.9, 0.25),
+                poison: Color::rgb(0.8, 0.4, 0.7),
+                rotten: Color::rgb(0.6, 0.45, 0.3),
+                wall: Color::GRAY,
+                title: Color::rgb(0.35, 0.7, 0.9),
+                text: Color::WHITE,
+                highlight: Color::rgb(0.9, 0.6, 0.0),
+                faded: Color::GRAY,
+            },
+            // Menus mark the selected row, so the highlight can stay white too
+            Palette::HighContrast => Colors {
+                food: Color::WHITE,
+                bonus: Color::WHITE,
+                poison: Color::WHITE,
+                rotten: Color::WHITE,
+                wall: Color::WHITE,
+                title: Color::WHITE,
+                text: Color::WHITE,
+                highlight: Color::WHITE,
+                faded: Color::WHITE,
+            },
+        }
+    }
+}
+
+impl Theme {
+    pub fn colors(&self) -> Colors {
+        self.palette.colors()
+    }
+
+    pub fn body_color(&self, player: Player, i: usize, len: usize) -> Color {
+        let [head, tail] = self.body_colors[player.0].map(Vec4::from);
+        let t = match len {
+            0 | 1 => 0.,
+            _ => i as f32 / (len - 1) as f32,
+        };
+        head.lerp(tail, t).into()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SnakeGlyphs {
+    // Pointing up, down, left and right, in the order of `DIRS`
+    pub heads: [char; 4],
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    // Thinner end pieces so the tail can be told from the head when the snake folds up
+    pub tail_horizontal: char,
+    pub tail_vertical: char,
+}
+
+impl SnakeGlyphs {
+    pub fn double_line() -> Self {
+        Self {
+            heads: ['▲', '▼', '◄', '►'],
+            horizontal: '═',
+            vertical: '║',
+            top_left: '╔',
+            top_right: '╗',
+            bottom_left: '╚',
+            bottom_right: '╝',
+            tail_horizontal: '─',
+            tail_vertical: '│',
+        }
+    }
+
+    pub fn single_line() -> Self {
+        Self {
+            heads: ['↑', '↓', '←', '→'],
+            horizontal: '─',
+            vertical: '│',
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+            tail_horizontal: '·',
+            tail_vertical: '·',
+        }
+    }
+
+    pub fn head(&self, dir: IVec2) -> Option<char> {
+        let i = DIRS.iter().position(|d| *d == dir)?;
+        Some(self.heads[i])
+    }
+
+    // Pick the piece connecting the segment at `i` to its neighbors in the body,
+    // `None` for a snake that only covers a single cell
+    pub fn segment(&self, body: &VecDeque<IVec2>, i: usize) -> Option<char> {
+        let pos = body[i];
+        // Skip neighbors on the same cell, the tail stacks up while growing
+        let prev = body.iter().take(i).rev().find(|p| **p != pos);
+        let next = body.iter().skip(i + 1).find(|p| **p != pos);
+        if prev.is_none() && next.is_none() {
+            return None;
+        }
+
+        // Neighbors across a wrapped edge are a full stage away
+        let dir_to = |p: IVec2| {
+            let d = p - pos;
+            IVec2::select(d.abs().cmpgt(IVec2::ONE), -d.signum(), d)
+        };
+        let has = |dir: IVec2| {
+            [prev, next]
+                .into_iter()
+                .flatten()
+                .any(|p| dir_to(*p) == dir)
+        };
+        let glyph = match (has(IVec2::Y), has(-IVec2::Y), has(-IVec2::X), has(IVec2::X)) {
+            (up, down, _, _) if next.is_none() && (up || down) => self.tail_vertical,
+            _ if next.is_none() => self.tail_horizontal,
+            (true, false, true, false) => self.bottom_right,
+            (true, false, false, true) => self.bottom_left,
+            (false, true, true, false) => self.top_right,
+            (false, true, false, true) => self.top_left,
+            (_, _, false, false) => self.vertical,
+            _ => self.horizontal,
+        };
+        Some(glyph)
+    }
+}
+
+#[derive(Component)]
+pub struct Popup {
+    pub pos: IVec2,
+    pub text: String,
+    pub timer: Timer,
+}
+
+pub struct RenderPlugin;
+
+impl Plugin for RenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(fit_terminal.after(crate::snake::spawn)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    // The last move is drawn before a crash takes the board down
+                    .with_system(
+                        render
+                            .after(crate::food::make_food)
+                            .before(crate::snake::die),
+                    )
+                    .with_system(fade_popups.after(render))
+                    .with_system(render_countdown.after(render))
+                    .with_system(food_effects.after(crate::food::eat))
+                    .with_system(crash_effects.after(crate::snake::die)),
+            )
+            .add_system_set(SystemSet::on_resume(GameState::Playing).with_system(redraw));
+    }
+}
+
+fn setup(mut commands: Commands, settings: Res<GameSettings>) {
+    let term = Terminal::with_size(settings.stage_size + 2);
+    commands
+        .spawn_bundle(TerminalBundle::from(term))
+        .insert(AutoCamera)
+        // Where touches land on the terminal, for the d-pad
+        .insert(ToWorld::default());
+}
+
+// Force `render` to draw the board over the pause box
+pub(crate) fn redraw(mut q_body: Query<&mut Body>) {
+    for mut body in &mut q_body {
+        body.set_changed();
+    }
+}
+
+// Easy plays on a bigger stage, the terminal follows whatever size the game is on
+fn fit_terminal(settings: Res<GameSettings>, mut q_term: Query<&mut Terminal>) {
+    let size = (settings.stage_size + 2).as_uvec2();
+    let mut term = q_term.single_mut();
+    if term.size() != size {
+        term.resize(size);
+    }
+}
+
+// Menus are laid out for the default stage, after a smaller one the terminal grows back
+// to fit them. Bigger ones are left as they are, the menus stay centered
+pub(crate) fn fit_menu(mut q_term: Query<&mut Terminal>) {
+    let mut term = q_term.single_mut();
+    let size = term.size().max((crate::STAGE_SIZE + 2).as_uvec2());
+    if term.size() != size {
+        term.resize(size);
+    }
+}
+
+pub(crate) fn render(
+    mut q_term: Query<&mut Terminal>,
+    q_snake: Query<(&Body, &Steering, &Player)>,
+    q_changed: Query<(), Changed<Body>>,
+    q_food: Query<(&Food, Option<&Rotten>)>,
+    (q_wall, q_portal, q_critter): (Query<&Wall>, Query<&Portal>, Query<&Critter>),
+    q_power_up: Query<&PowerUp>,
+    q_popup: Query<&Popup>,
+    (theme, loc, accessibility): (Res<Theme>, Res<Localization>, Res<Accessibility>),
+    (score, combo, high_scores, zen): (Res<Score>, Res<Combo>, Res<HighScores>, Res<Zen>),
+    stats: Res<RunStats>,
+    players: Res<Players>,
+    level: Res<Level>,
+    settings: Res<GameSettings>,
+    bounds: Res<ShrinkingBounds>,
+    ghost: Res<Ghost>,
+    q_particle: Query<&Particle>,
+) {
+    // Particles and smooth movement change between steps, so the board keeps being drawn
+    if q_changed.is_empty() && q_particle.is_empty() && !theme.smooth {
+        return;
+    }
+
+    let first = q_snake.iter().find(|(_, _, player)| player.0 == 0);
+    if let Some((body, steering, _)) = first {
+        let body = &body.0;
+        let colors = theme.colors();
+        let shapes = accessibility.shapes(&settings);
+        let mut term = q_term.single_mut();
+
+        term.clear();
+        term.draw_border(BorderGlyphs::single_line());
+        // Hud sits inside the top and bottom borders, leaving the corners intact
+        let secs = stats.time as u32;
+        let clock = format!("{}:{:02}", secs / 60, secs % 60);
+        let time = format!(" {} ", loc.format("hud.time", &[&clock]));
+        let time_x = locale::width(&time) + 1;
+        let tier = settings.speed_tier(steering.speed);
+        let speed = format!("{:.1}", steering.speed);
+        let speed = format!(" {} ", loc.format("hud.speed", &[&speed, &tier]));
+        let speed_x = locale::width(&speed) + 1;
+        match *players {
+            Players::One => {
+                // The best run so far to beat next to the score, left out when there isn't
+                // one or it doesn't fit. The score lights up once it's been passed. Zen runs
+                // go up against their own table
+                let table = match zen.enabled {
+                    true => &high_scores.zen,
+                    false => &high_scores.runs,
+                };
+                let best = table.first().map_or(0, |run| run.score);
+                let describe = combo.describe(0, score.0[0]);
+                let hud = |score: &String| {
+                    format!(" {} ", loc.format("hud.score", &[score, &level.number]))
+                };
+                let with_best = hud(&loc.format("hud.best", &[&describe, &best]));
+                let fits = locale::width(&with_best) + time_x + 2 <= term.size().x as i32;
+                let text = match best > 0 && fits {
+                    true => with_best,
+                    false => hud(&describe),
+                };
+                let color = match best > 0 && score.0[0] > best {
+                    true => colors.highlight,
+                    false => colors.text,
+                };
+                term.put_string([2, 0].pivot(Pivot::TopLeft), text.fg(color));
+                term.put_string([time_x, 0].pivot(Pivot::TopRight), time.fg(colors.text));
+                term.put_string(
+                    [2, 0].pivot(Pivot::BottomLeft),
+                    format!(" {} ", loc.format("hud.length", &[&body.len()])).fg(colors.text),
+                );
+                term.put_string(
+                    [speed_x, 0].pivot(Pivot::BottomRight),
+                    speed.fg(colors.text),
+                );
+            }
+            // Each score in the color of its snake's head
+            Players::Two | Players::Computer => {
+                let [p1, p2] = [0, 1].map(|i| theme.body_colors[i][0]);
+                let player = |i| {
+                    let name = players.name(i, &loc);
+                    format!(
+                        " {} ",
+                        loc.format("hud.player", &[&name, &combo.describe(i, score.0[i])])
+                    )
+                };
+                let p2_score = player(1);
+                let p2_x = locale::width(&p2_score) + 1;
+                term.put_string([2, 0].pivot(Pivot::TopLeft), player(0).fg(p1));
+                term.put_string([p2_x, 0].pivot(Pivot::TopRight), p2_score.fg(p2));
+                term.put_string([2, 0].pivot(Pivot::BottomLeft), time.fg(colors.text));
+                let level = format!(" {} ", loc.format("hud.level", &[&level.number]));
+                let level_x = locale::width(&level) + 1;
+                term.put_string(
+                    [level_x, 0].pivot(Pivot::BottomRight),
+                    level.fg(colors.text),
+                );
+            }
+        }
+        for wall in &q_wall {
+            let pos = settings.to_term(wall.pos);
+            term.put_char(pos, settings.wall_glyph.fg(colors.wall));
+        }
+        for portal in &q_portal {
+            let pos = settings.to_term(portal.pos);
+            term.put_char(pos, settings.portal_glyph.fg(colors.title));
+        }
+        for p in settings.stage_cells() {
+            let pos = settings.to_term(p);
+            if !bounds.is_safe(p, &settings) {
+                term.put_char(pos, shapes.hazard.fg(colors.wall));
+            } else if bounds.is_closing(p, &settings) {
+                term.put_char(pos, shapes.closing.fg(colors.poison));
+            }
+        }
+        // Where the best run was after as many moves, under everything else on the board.
+        // Single player only, the path stops where that run ended. A continued game has lost
+        // count of its moves
+        if ghost.visible && *players == Players::One && !stats.resumed {
+            let tick = stats.path.len();
+            let trail = ghost
+                .path
+                .get(tick.saturating_sub(GHOST_TRAIL)..=tick)
+                .unwrap_or_default();
+            for p in trail.iter().filter(|p| settings.in_bounds(**p)) {
+                let pos = settings.to_term(*p);
+                term.put_char(pos, shapes.ghost.fg(colors.faded));
+            }
+        }
+        for (food, rotten) in &q_food {
+            let pos = settings.to_term(food.pos);
+            let glyph = match food.ttl {
+                _ if food.poison => shapes.poison.fg(colors.poison),
+                _ if rotten.is_some() => shapes.rotten.fg(colors.rotten),
+                Some(_) => shapes.bonus.fg(colors.bonus),
+                None => shapes.food.fg(colors.food),
+            };
+            term.put_char(pos, glyph);
+        }
+        for power_up in &q_power_up {
+            let pos = settings.to_term(power_up.pos);
+            term.put_char(pos, power_up.kind.glyph().fg(power_up.kind.color()));
+        }
+        for critter in &q_critter {
+            let pos = settings.to_term(critter.pos);
+            term.put_char(pos, critter.kind.glyph().fg(critter.kind.color()));
+        }
+        particles::draw(&mut term, &q_particle, &settings);
+        for (body, steering, player) in &q_snake {
+            let body = &body.0;
+            let glyphs = accessibility.snake_glyphs(player.0, &theme);
+            // Draw tail first so the head always ends up on top
+            for (i, pos) in body.iter().enumerate().rev() {
+                let pos = settings.to_term(*pos);
+                let glyph = match i {
+                    0 => glyphs
+                        .as_ref()
+                        .and_then(|glyphs| glyphs.head(steering.dir))
+                        .unwrap_or(settings.head_glyph),
+                    _ => glyphs
+                        .as_ref()
+                        .and_then(|glyphs| glyphs.segment(body, i))
+                        .unwrap_or(settings.body_glyph),
+                };
+                term.put_char(pos, glyph.fg(theme.body_color(*player, i, body.len())));
+            }
+            if theme.smooth && steering.cell_pos >= 0.5 {
+                draw_half_step(&mut term, body, steering.dir, *player, &theme, &settings);
+            }
+        }
+        for popup in &q_popup {
+            let len = locale::width(&popup.text);
+            let pos = settings.to_term(popup.pos) + IVec2::Y;
+            let max = settings.stage_size - IVec2::new(len - 1, 0);
+            let pos = pos.clamp(IVec2::ONE, max);
+            term.put_string(pos, popup.text.as_str().fg(colors.highlight));
+        }
+    }
+}
+
+// The head reaching into the next cell and the tail pulling out of the last one, each by
+// half a cell. Nothing is drawn where the step wraps around the stage or the tail is
+// stacked while growing
+fn draw_half_step(
+    term: &mut Terminal,
+    body: &VecDeque<IVec2>,
+    dir: IVec2,
+    player: Player,
+    theme: &Theme,
+    settings: &GameSettings,
+) {
+    let len = body.len();
+    let next = body[0] + dir;
+    if settings.in_bounds(next) && !body.contains(&next) {
+        let color = theme.body_color(player, 0, len);
+        term.put_char(settings.to_term(next), half_block(-dir).fg(color));
+    }
+    if len < 2 {
+        return;
+    }
+    let toward = body[len - 2] - body[len - 1];
+    if DIRS.contains(&toward) {
+        let color = theme.body_color(player, len - 1, len);
+        term.put_char(
+            settings.to_term(body[len - 1]),
+            half_block(toward).fg(color),
+        );
+    }
+}
+
+// The half of a cell on the `dir` side
+fn half_block(dir: IVec2) -> char {
+    match (dir.x, dir.y) {
+        (1, _) => '▐',
+        (-1, _) => '▌',
+        (_, 1) => '▀',
+        _ => '▄',
+    }
+}
+
+// Drawn over the board, the next move clears the last "Go!"
+fn render_countdown(
+    mut q_term: Query<&mut Terminal>,
+    countdown: Res<Countdown>,
+    loc: Res<Localization>,
+) {
+    let timer = &countdown.0;
+    if timer.finished() && !timer.just_finished() {
+        return;
+    }
+
+    let text = match timer.duration().as_secs_f32() - timer.elapsed_secs() {
+        left if left > 0.0 => format!(" {} ", left.ceil()),
+        _ => loc.get("hud.go"),
+    };
+    let mut term = q_term.single_mut();
+    term.put_string([locale::centered(&text), 3].pivot(Pivot::Center), text);
+}
+
+fn food_effects(
+    mut eaten: EventReader<FoodEaten>,
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    theme: Res<Theme>,
+) {
+    for food in eaten.iter().filter(|food| !food.spoiled) {
+        let color = match food.bonus {
+            true => theme.colors().bonus,
+            false => theme.colors().food,
+        };
+        commands
+            .spawn()
+            .insert(juice::Effect::pulse(settings.to_term(food.pos), color));
+        particles::burst(&mut commands, food.pos, color);
+    }
+}
+
+// The snakes are only despawned once commands are applied, so they're still around to
+// break apart
+fn crash_effects(
+    mut died: EventReader<SnakeDied>,
+    q_snake: Query<(&Body, &Player)>,
+    mut commands: Commands,
+    theme: Res<Theme>,
+) {
+    if died.iter().count() == 0 {
+        return;
+    }
+    for (body, player) in &q_snake {
+        let len = body.0.len();
+        let segments = body.0.iter().enumerate();
+        let colored = segments.map(|(i, p)| (*p, theme.body_color(*player, i, len)));
+        particles::scatter(&mut commands, colored);
+    }
+    commands.spawn().insert(juice::Effect::shake());
+    commands.spawn().insert(juice::Effect::flash(Color::RED));
+}
+
+fn fade_popups(time: Res<Time>, mut q_popup: Query<(Entity, &mut Popup)>, mut commands: Commands) {
+    for (entity, mut popup) in &mut q_popup {
+        if popup.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use bevy::prelude::{App, IVec2, Plugin};
+
+use crate::{accessibility::SPEED_FLOORS, Difficulty, GameSettings, Layout, StageSize};
+
+const FILE_NAME: &str = "replay.txt";
+
+// Every direction change committed during a game, keyed by the tick it happened on.
+// Together with the rng seed, wall mode, layout, difficulty, stage size, speed floor and mode
+// this is enough to play the same game again.
+#[derive(Clone, Default)]
+pub struct Recording {
+    pub seed: u64,
+    // Whether the snake wrapped around the stage edges
+    pub wrap: bool,
+    // Wall layout that was picked, saved by its index
+    pub layout: Layout,
+    // Difficulty preset saved by its index, recordings made before it was saved count as Normal
+    pub difficulty: Difficulty,
+    // Whether the arena shrank, false for recordings made before it could
+    pub shrink: bool,
+    // Whether it was a speedrun, which ends once the snake is long enough
+    pub speedrun: bool,
+    // Older recordings were all played on the normal stage at full speed
+    pub stage_size: StageSize,
+    pub speed_floor: f32,
+    pub tick: usize,
+    pub turns: Vec<(usize, IVec2)>,
+    // Ticks where boosting started or stopped
+    pub boosts: Vec<(usize, bool)>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, wrap: bool, layout: Layout, difficulty: Difficulty) -> Self {
+        Self {
+            seed,
+            wrap,
+            layout,
+            difficulty,
+            speed_floor: SPEED_FLOORS[0],
+            ..Default::default()
+        }
+    }
+
+    // The speed the snakes start every level from, before any level speed-up
+    pub fn start_speed(&self, settings: &GameSettings) -> f32 {
+        settings.start_speed * self.speed_floor
+    }
+
+    pub fn record(&mut self, dir: IVec2) {
+        self.turns.push((self.tick, dir));
+    }
+
+    pub fn record_boost(&mut self, boosting: bool) {
+        if self.boosting_at(self.tick) != boosting {
+            self.boosts.push((self.tick, boosting));
+        }
+    }
+
+    pub fn boosting_at(&self, tick: usize) -> bool {
+        self.boosts
+            .iter()
+            .rev()
+            .find(|(boost_tick, _)| *boost_tick <= tick)
+            .is_some_and(|(_, boosting)| *boosting)
+    }
+
+    pub fn turn_at(&self, tick: usize) -> Option<IVec2> {
+        self.turns
+            .iter()
+            .find(|(turn_tick, _)| *turn_tick == tick)
+            .map(|(_, dir)| *dir)
+    }
+
+    // "seed wrap layout difficulty shrink speedrun stage_size speed_floor" on the first line, then one "tick x y" line per
+    // turn and one "tick boost on" line per boost change
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} {} {} {} {} {} {} {}",
+            self.seed,
+            self.wrap,
+            self.layout.index(),
+            self.difficulty.index(),
+            self.shrink,
+            self.speedrun,
+            self.stage_size.index(),
+            self.speed_floor
+        );
+        for (tick, dir) in &self.turns {
+            text += &format!("\n{} {} {}", tick, dir.x, dir.y);
+        }
+        for (tick, boosting) in &self.boosts {
+            text += &format!("\n{} boost {}", tick, boosting);
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let mut header = lines.next()?.split_whitespace();
+        let seed = header.next()?.parse().ok()?;
+        let wrap = header.next()?.parse().ok()?;
+        let layout = *Layout::ALL.get(header.next()?.parse::<usize>().ok()?)?;
+        let difficulty = match header.next() {
+            Some(difficulty) => *Difficulty::ALL.get(difficulty.parse::<usize>().ok()?)?,
+            None => Difficulty::Normal,
+        };
+        let mut recording = Recording::new(seed, wrap, layout, difficulty);
+        recording.shrink = match header.next() {
+            Some(shrink) => shrink.parse().ok()?,
+            None => false,
+        };
+        recording.speedrun = match header.next() {
+            Some(speedrun) => speedrun.parse().ok()?,
+            None => false,
+        };
+        if let Some(stage_size) = header.next() {
+            recording.stage_size = *StageSize::ALL.get(stage_size.parse::<usize>().ok()?)?;
+        }
+        if let Some(floor) = header.next() {
+            let floor: f32 = floor.parse().ok()?;
+            recording.speed_floor = *SPEED_FLOORS.iter().find(|f| **f == floor)?;
+        }
+        for line in lines {
+            if let [tick, "boost", boosting] = line.split_whitespace().collect::<Vec<_>>()[..] {
+                let boost = (tick.parse().ok()?, boosting.parse().ok()?);
+                recording.boosts.push(boost);
+                continue;
+            }
+            let mut fields = line
+                .split_whitespace()
+                .map(|field| field.parse::<i64>().ok());
+            let (tick, x, y) = (fields.next()??, fields.next()??, fields.next()??);
+            let tick = usize::try_from(tick).ok()?;
+            recording.turns.push((tick, IVec2::new(x as i32, y as i32)));
+        }
+        Some(recording)
+    }
+}
+
+// A recording being played back instead of reading the controls
+#[derive(Default)]
+pub struct Replay(pub Option<Recording>);
+
+// Where the replay to watch is read from, the last saved game unless `--replay <file>` is given
+pub struct ReplayFile(pub Option<String>);
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let args: Vec<_> = std::env::args().collect();
+        let file = args
+            .iter()
+            .position(|arg| arg == "--replay")
+            .and_then(|i| args.get(i + 1))
+            .cloned();
+        app.init_resource::<Recording>()
+            .init_resource::<Replay>()
+            .insert_resource(ReplayFile(file));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(file: &ReplayFile) -> Option<Recording> {
+    let path = match &file.0 {
+        Some(path) => path.into(),
+        None => path()?,
+    };
+    Recording::from_text(&std::fs::read_to_string(path).ok()?)
+}
+
+// Stored next to the executable, each game overwrites the last one
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(recording: &Recording) {
+    if let Some(path) = path() {
+        std::fs::write(path, recording.to_text()).ok();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path() -> Option<std::path::PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(FILE_NAME))
+}
+
+// No file system on the web, replays can't be saved or loaded
+#[cfg(target_arch = "wasm32")]
+pub fn load(_file: &ReplayFile) -> Option<Recording> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(_recording: &Recording) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_replays_are_rejected() {
+        let text = Recording::new(7, false, Layout::Random, Difficulty::Normal).to_text();
+        assert!(Recording::from_text(&text).is_some());
+        assert!(Recording::from_text("").is_none());
+        assert!(Recording::from_text("seven false 0").is_none());
+        assert!(Recording::from_text("7 maybe 0").is_none());
+        assert!(Recording::from_text("7 false").is_none());
+        // A stage size or a speed floor that the game doesn't have
+        assert!(Recording::from_text("7 false 0 1 false false 9").is_none());
+        assert!(Recording::from_text("7 false 0 1 false false 0 0.123").is_none());
+        // Turns and boosts that can't be read throw the whole replay out
+        for line in ["-1 0 1", "3 0", "3 x 1", "3 boost maybe"] {
+            assert!(Recording::from_text(&format!("{}\n{}", text, line)).is_none());
+        }
+    }
+
+    #[test]
+    fn replay_with_unknown_layout_is_rejected() {
+        let text = Recording::new(7, false, Layout::Random, Difficulty::Normal).to_text();
+        let bad = text.replacen(" 0 1 ", &format!(" {} 1 ", Layout::ALL.len()), 1);
+        assert_ne!(text, bad);
+        assert!(Recording::from_text(&bad).is_none());
+    }
+
+    #[test]
+    fn replay_with_unknown_difficulty_is_rejected() {
+        let text = Recording::new(7, false, Layout::Random, Difficulty::Normal).to_text();
+        let bad = text.replacen(" 0 1 ", &format!(" 0 {} ", Difficulty::ALL.len()), 1);
+        assert_ne!(text, bad);
+        assert!(Recording::from_text(&bad).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::{warn, Color, IVec2};
+use serde::Deserialize;
+
+use crate::controls::{self, Action, KeyBindings};
+use crate::volume::AudioSettings;
+use crate::GameSettings;
+
+const FILE_NAME: &str = "snake.toml";
+// Smallest stage the file can ask for, the small stage size takes ten off each side of it
+const MIN_STAGE_SIZE: IVec2 = IVec2::from_array([20, 20]);
+
+// Everything `snake.toml` can set, keys that are left out keep their defaults. The
+// fields of `GameSettings` go at the top, e.g. `stage_size = [30, 30]` or `food_glyph = "*"`
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsFile {
+    #[serde(flatten)]
+    pub game: GameSettings,
+    // Foods kept on the board at once, bonus food included but not poison
+    pub max_food: Option<usize>,
+    // Head and tail color of each player's snake as hex, e.g. [["99ff80", "1a591a"], ...]
+    body_colors: Option<[[String; 2]; 2]>,
+    // Key names for each action under a `[bindings]` table, e.g. `Pause = ["Escape", "P"]`
+    bindings: BTreeMap<String, Vec<String>>,
+    volume: Option<f32>,
+    muted: Option<bool>,
+    // Share of `volume` the background music and the sound effects play at
+    music_volume: Option<f32>,
+    sfx_volume: Option<f32>,
+    // Where runs are sent and the top runs are fetched from
+    #[cfg(feature = "leaderboard")]
+    pub leaderboard_url: Option<String>,
+}
+
+impl SettingsFile {
+    // `None` unless all four colors can be read
+    pub fn body_colors(&self) -> Option<[[Color; 2]; 2]> {
+        let [p1, p2] = self.body_colors.as_ref()?;
+        let pair =
+            |[head, tail]: &[String; 2]| Some([Color::hex(head).ok()?, Color::hex(tail).ok()?]);
+        Some([pair(p1)?, pair(p2)?])
+    }
+
+    // Bindings and volume set here win over the ones changed in game. Unknown actions and keys
+    // are skipped
+    pub fn apply_bindings(&self, bindings: &mut KeyBindings) {
+        for (action, keys) in &self.bindings {
+            let keys: Vec<_> = keys
+                .iter()
+                .filter_map(|key| controls::key_from_name(key))
+                .collect();
+            if let Some(action) = Action::from_name(action) {
+                if !keys.is_empty() {
+                    bindings.set(action, keys);
+                }
+            }
+        }
+    }
+
+    pub fn apply_audio(&self, audio: &mut AudioSettings) {
+        if let Some(volume) = self.volume {
+            audio.volume = volume.clamp(0.0, 1.0);
+        }
+        if let Some(muted) = self.muted {
+            audio.muted = muted;
+        }
+        if let Some(music) = self.music_volume {
+            audio.music = music.clamp(0.0, 1.0);
+        }
+        if let Some(sfx) = self.sfx_volume {
+            audio.sfx = sfx.clamp(0.0, 1.0);
+        }
+    }
+}
+
+impl GameSettings {
+    // Chances, stage size and the tick counts that other counts are divided by are kept to
+    // what the game can play with
+    pub fn checked(mut self) -> Self {
+        let chance = |p: f64| if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+        self.bonus_chance = chance(self.bonus_chance);
+        self.poison_chance = chance(self.poison_chance);
+        self.power_up_chance = chance(self.power_up_chance);
+        self.boost_shrink_ticks = self.boost_shrink_ticks.max(1);
+        self.critter_ticks = self.critter_ticks.max(1);
+        self.shrink_ticks = self.shrink_ticks.max(1);
+        self.stage_size = self.stage_size.max(MIN_STAGE_SIZE);
+        self
+    }
+}
+
+// A file that can't be read is logged and counts as empty
+pub fn from_text(text: &str) -> SettingsFile {
+    toml::from_str(text).unwrap_or_else(|err| {
+        warn!("Couldn't read {}, using the defaults: {}", FILE_NAME, err);
+        SettingsFile::default()
+    })
+}
+
+// Read from next to the executable, a missing file counts as empty
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> SettingsFile {
+    let exe = std::env::current_exe().ok();
+    exe.as_deref()
+        .and_then(|exe| exe.parent())
+        .and_then(|dir| std::fs::read_to_string(dir.join(FILE_NAME)).ok())
+        .map_or_else(SettingsFile::default, |text| from_text(&text))
+}
+
+// No file system on the web, the defaults are all there is
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> SettingsFile {
+    SettingsFile::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{STAGE_SIZE, START_SPEED};
+
+    #[test]
+    fn settings_file_overrides_defaults() {
+        let file: SettingsFile = toml::from_str(
+            r#"
+            stage_size = [20, 30]
+            food_glyph = "*"
+            max_food = 3
+            body_colors = [["ffffff", "000000"], ["ff0000", "00ff00"]]
+
+            [bindings]
+            Pause = ["Q"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.game.stage_size, IVec2::new(20, 30));
+        assert_eq!(file.game.food_glyph, '*');
+        assert_eq!(file.game.start_speed, START_SPEED);
+        assert_eq!(file.max_food, Some(3));
+        assert_eq!(file.body_colors().unwrap()[1][0], Color::RED);
+
+        let mut bindings = KeyBindings::default();
+        file.apply_bindings(&mut bindings);
+        assert_eq!(bindings.describe(Action::Pause), "Q");
+        assert_eq!(bindings.describe(Action::Up), "W");
+    }
+
+    #[test]
+    fn bad_settings_are_kept_playable() {
+        // A file that doesn't parse is dropped as a whole
+        assert_eq!(from_text("stage_size = [20").game.stage_size, STAGE_SIZE);
+        assert_eq!(from_text("max_speed = \"fast\"").max_food, None);
+
+        let file = from_text(
+            r#"
+            stage_size = [4, 200]
+            bonus_chance = 3.0
+            poison_chance = -1.0
+            power_up_chance = nan
+            boost_shrink_ticks = 0
+            critter_ticks = 0
+            shrink_ticks = 0
+            "#,
+        );
+        let settings = file.game.checked();
+        assert_eq!(settings.stage_size, IVec2::new(20, 200));
+        assert_eq!(settings.bonus_chance, 1.0);
+        assert_eq!(settings.poison_chance, 0.0);
+        assert_eq!(settings.power_up_chance, 0.0);
+        assert_eq!(settings.boost_shrink_ticks, 1);
+        assert_eq!(settings.critter_ticks, 1);
+        assert_eq!(settings.shrink_ticks, 1);
+    }
+}
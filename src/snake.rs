@@ -0,0 +1,1149 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::accessibility::Accessibility;
+use crate::audio::Sounds;
+use crate::daily::Daily;
+use crate::editor::{CustomLevel, LevelFile};
+use crate::food::{Food, FoodCount, FoodEaten, PowerUp, PowerUpKind, Rotten};
+use crate::juice;
+use crate::practice::Practice;
+use crate::replay::{Recording, Replay};
+use crate::speedrun::Speedrun;
+use crate::volume::AudioSettings;
+use crate::zen::Zen;
+use crate::{
+    set_difficulty, Countdown, Difficulty, GameMode, GameRng, GameSettings, GameState, Layout,
+    Level, Players, RunStats, Score, ShrinkingBounds, StageSize, WallMode, Winner, DIRS,
+};
+
+// Cells critters are kept from the snakes' heads when they're let loose
+const CRITTER_DISTANCE: i32 = 6;
+const SPIDER_TURN_CHANCE: f64 = 0.25;
+
+#[derive(Component)]
+pub struct Wall {
+    pub pos: IVec2,
+}
+
+// One end of a pair placed with the walls, the head goes in here and comes out next to `exit`
+#[derive(Component)]
+pub struct Portal {
+    pub pos: IVec2,
+    pub exit: IVec2,
+}
+
+// Roams the stage on its own, running into one is as bad as running into a wall
+#[derive(Component)]
+pub struct Critter {
+    pub pos: IVec2,
+    pub kind: CritterKind,
+    pub dir: IVec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CritterKind {
+    // Heads for the nearest food and carries it off
+    Rat,
+    // Wanders about, turning now and then
+    Spider,
+}
+
+impl CritterKind {
+    pub const ALL: [CritterKind; 2] = [CritterKind::Rat, CritterKind::Spider];
+
+    pub fn glyph(self) -> char {
+        match self {
+            CritterKind::Rat => 'r',
+            CritterKind::Spider => '¥',
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            CritterKind::Rat => Color::rgb(0.65, 0.55, 0.45),
+            CritterKind::Spider => Color::ORANGE_RED,
+        }
+    }
+}
+
+// Walls, portals and critters, which come and go together with each layout
+pub type InLayout = Or<(With<Wall>, With<Portal>, With<Critter>)>;
+
+#[derive(Component)]
+pub struct GridPos(pub IVec2);
+
+#[derive(Component)]
+pub struct Steering {
+    pub cell_pos: f32,
+    pub dir: IVec2,
+    pub prev: IVec2,
+    pub speed: f32,
+    // Turns pressed since the last tick, applied one per tick
+    pub turns: VecDeque<IVec2>,
+    pub turned_at: f64,
+    // Boost is held, and how many boosted ticks were taken in this game
+    pub boosting: bool,
+    pub boost_ticks: usize,
+}
+
+impl Steering {
+    // Heading up from `start`
+    pub fn new(start: IVec2, speed: f32) -> Self {
+        Self {
+            cell_pos: 0.5,
+            dir: IVec2::Y,
+            speed,
+            prev: start,
+            turns: VecDeque::new(),
+            turned_at: 0.0,
+            boosting: false,
+            boost_ticks: 0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Body(pub VecDeque<IVec2>);
+
+// Index of the player steering the snake, the second one is on the arrow keys
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct Player(pub usize);
+
+// Power-ups the snake is under and the moves each one has left
+#[derive(Component, Default)]
+pub struct Effects(pub Vec<(PowerUpKind, usize)>);
+
+impl Effects {
+    pub fn has(&self, kind: PowerUpKind) -> bool {
+        self.0.iter().any(|(active, _)| *active == kind)
+    }
+}
+
+// Steered by `steer_ai` instead of the keys, heading for food at most `lookahead` moves away
+#[derive(Component)]
+pub struct AiSnake {
+    pub lookahead: usize,
+}
+
+#[derive(Component)]
+pub struct Grow {
+    pub turns: usize,
+    pub pos: IVec2,
+    pub player: usize,
+}
+
+// Segments to drop from the tail of the player's snake on its next move
+#[derive(Component)]
+pub struct Shrink {
+    pub segments: usize,
+    pub player: usize,
+}
+
+// Sent by `grow` for every segment added to a snake
+pub struct SnakeGrew {
+    pub player: usize,
+    pub length: usize,
+}
+
+// Sent by `die` for every snake that crashed, before any of them are despawned
+pub struct SnakeDied {
+    pub player: usize,
+    pub pos: IVec2,
+    pub cause: DeathCause,
+}
+
+// Sent by `die` in zen games in place of `SnakeDied`, once each time a snake runs into
+// something it goes through
+pub struct Bonk {
+    pub player: usize,
+    pub pos: IVec2,
+}
+
+// What a snake crashed into, the first that applies when it's more than one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    // Poison ate the whole snake
+    Starved,
+    Body,
+    OtherSnake,
+    Critter,
+    // The edge of the stage with solid walls
+    Edge,
+    Wall,
+    // Outside the shrinking arena
+    Hazard,
+}
+
+impl DeathCause {
+    pub const ALL: [DeathCause; 7] = [
+        DeathCause::Starved,
+        DeathCause::Body,
+        DeathCause::OtherSnake,
+        DeathCause::Critter,
+        DeathCause::Edge,
+        DeathCause::Wall,
+        DeathCause::Hazard,
+    ];
+}
+
+// Sent by `apply_effects` for every power-up a snake runs over
+pub struct PowerUpTaken {
+    pub player: usize,
+    pub pos: IVec2,
+    pub kind: PowerUpKind,
+}
+
+// Sent by `drive` whenever the first snake moves a cell, with the recording's new tick
+pub struct TickAdvanced {
+    pub tick: usize,
+}
+
+pub struct SnakePlugin;
+
+impl Plugin for SnakePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SnakeGrew>()
+            .add_event::<SnakeDied>()
+            .add_event::<Bonk>()
+            .add_event::<PowerUpTaken>()
+            .add_event::<TickAdvanced>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(spawn)
+                    .with_system(reset_mode.after(spawn)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(steer_ai.after(crate::input::input))
+                    .with_system(drive.after(steer_ai))
+                    .with_system(apply_effects.after(drive))
+                    .with_system(speed_up.after(crate::food::eat))
+                    .with_system(grow.after(crate::food::eat))
+                    .with_system(shrink.after(grow))
+                    .with_system(track_stats.after(shrink))
+                    .with_system(shrink_arena.after(drive))
+                    .with_system(roam_critters.after(crate::food::eat).after(shrink_arena))
+                    .with_system(spawn_critters.after(roam_critters))
+                    .with_system(die.after(crate::food::make_food))
+                    .with_system(
+                        level_up
+                            .after(crate::ui::end_game)
+                            .after(crate::input::pause),
+                    ),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::LevelUp).with_system(next_level));
+    }
+}
+
+// Replays pick the mode they were recorded with, which `spawn` doesn't know about.
+// Speedruns are single player
+fn reset_mode(
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    mut bounds: ResMut<ShrinkingBounds>,
+    mut speedrun: ResMut<Speedrun>,
+    mut recording: ResMut<Recording>,
+    replay: Res<Replay>,
+    settings: Res<GameSettings>,
+) {
+    let (shrink, timed) = match &replay.0 {
+        Some(replay) => (replay.shrink, replay.speedrun),
+        None => (
+            *mode == GameMode::Shrinking,
+            *mode == GameMode::Speedrun && *players == Players::One,
+        ),
+    };
+    bounds.enabled = shrink;
+    speedrun.start(timed);
+    recording.shrink = shrink;
+    recording.speedrun = timed;
+    bounds.rings = 0;
+    bounds.tick = 0;
+    bounds.next = settings.shrink_ticks;
+}
+
+// Food and power-ups caught by the closing ring are taken away, `make_food` puts new food
+// down inside
+fn shrink_arena(
+    mut ticks: EventReader<TickAdvanced>,
+    settings: Res<GameSettings>,
+    mut bounds: ResMut<ShrinkingBounds>,
+    q_food: Query<(Entity, &Food)>,
+    q_power_up: Query<(Entity, &PowerUp)>,
+    mut commands: Commands,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    let tick = match ticks.iter().last() {
+        Some(advanced) if bounds.enabled => advanced.tick,
+        _ => return,
+    };
+    bounds.tick = tick;
+    if bounds.tick < bounds.next || !bounds.can_shrink(&settings) {
+        return;
+    }
+
+    bounds.rings += 1;
+    bounds.next += settings.shrink_ticks.max(1);
+    let caught = q_food
+        .iter()
+        .map(|(e, food)| (e, food.pos))
+        .chain(q_power_up.iter().map(|(e, power_up)| (e, power_up.pos)));
+    for (e, pos) in caught {
+        if !bounds.is_safe(pos, &settings) {
+            commands.entity(e).despawn();
+        }
+    }
+    commands.spawn().insert(juice::Effect::flash(Color::ORANGE));
+    audio.play(sfx.ouch.clone()).with_volume(volume.sfx_gain());
+}
+
+pub(crate) fn spawn(
+    mut commands: Commands,
+    mut count: ResMut<FoodCount>,
+    mut score: ResMut<Score>,
+    mut winner: ResMut<Winner>,
+    mut countdown: ResMut<Countdown>,
+    mut stats: ResMut<RunStats>,
+    mut rng: ResMut<GameRng>,
+    mut recording: ResMut<Recording>,
+    mut wall_mode: ResMut<WallMode>,
+    mut layout: ResMut<Layout>,
+    replay: Res<Replay>,
+    (players, accessibility, custom, daily): (
+        Res<Players>,
+        Res<Accessibility>,
+        Res<CustomLevel>,
+        Res<Daily>,
+    ),
+    (mut difficulty, mut stage_size): (ResMut<Difficulty>, ResMut<StageSize>),
+    mut settings: ResMut<GameSettings>,
+    mut level: ResMut<Level>,
+    q_wall: Query<Entity, InLayout>,
+) {
+    let seed = match &replay.0 {
+        Some(replay) => {
+            *wall_mode = if replay.wrap {
+                WallMode::Wrap
+            } else {
+                WallMode::Solid
+            };
+            *layout = replay.layout;
+            set_difficulty(&mut difficulty, &mut settings, replay.difficulty);
+            settings.stage_size += replay.stage_size.extra_space() - stage_size.extra_space();
+            *stage_size = replay.stage_size;
+            replay.seed
+        }
+        None if daily.enabled => daily.seed(),
+        None => settings.seed.unwrap_or_else(rand::random),
+    };
+    // Replays are played back on the layout they were recorded with, and daily games are
+    // on the day's
+    let custom = custom
+        .level()
+        .filter(|_| replay.0.is_none() && !daily.enabled);
+
+    rng.0 = StdRng::seed_from_u64(seed);
+    *recording = Recording::new(seed, *wall_mode == WallMode::Wrap, *layout, *difficulty);
+    recording.stage_size = *stage_size;
+    recording.speed_floor = match &replay.0 {
+        Some(replay) => replay.speed_floor,
+        None => accessibility.speed_floor,
+    };
+
+    // Replays only record the first snake
+    let snakes = match (*players, &replay.0) {
+        (Players::Two | Players::Computer, None) => 2,
+        _ => 1,
+    };
+    for player in 0..snakes {
+        let start = start_pos(player, snakes, custom, &settings);
+        let mut snake = commands.spawn();
+        snake
+            .insert(Body(VecDeque::from(vec![start])))
+            .insert(Steering::new(start, recording.start_speed(&settings)))
+            .insert(GridPos(start))
+            .insert(Player(player))
+            .insert(Effects::default());
+        if player == 1 && *players == Players::Computer {
+            snake.insert(AiSnake {
+                lookahead: difficulty.ai_lookahead(),
+            });
+        }
+    }
+    *count = FoodCount::default();
+    *score = Score::default();
+    winner.0 = None;
+    *level = Level::default();
+    *stats = RunStats::default();
+    countdown.0 = Timer::from_seconds(settings.countdown, false);
+
+    // Each game gets a fresh layout
+    q_wall.for_each(|e| commands.entity(e).despawn());
+    if let Some(custom) = custom {
+        spawn_custom(&mut commands, custom, snakes, &settings);
+    } else if difficulty.walls() {
+        spawn_layout(&mut commands, *layout, &settings, &mut rng.0);
+    }
+}
+
+fn spawn_layout(
+    commands: &mut Commands,
+    layout: Layout,
+    settings: &GameSettings,
+    rng: &mut StdRng,
+) {
+    for pos in wall_layout(layout, settings, rng) {
+        commands.spawn().insert(Wall { pos });
+    }
+    for (a, b) in portal_layout(layout, settings) {
+        commands.spawn().insert(Portal { pos: a, exit: b });
+        commands.spawn().insert(Portal { pos: b, exit: a });
+    }
+}
+
+// Custom levels are played whatever the difficulty, and without the critters that come
+// with the other layouts. No wall goes where a snake starts
+fn spawn_custom(
+    commands: &mut Commands,
+    level: &LevelFile,
+    snakes: usize,
+    settings: &GameSettings,
+) {
+    let starts: Vec<_> = (0..snakes)
+        .map(|player| start_pos(player, snakes, Some(level), settings))
+        .collect();
+    for pos in level.walls.iter().copied().map(IVec2::from) {
+        if settings.in_bounds(pos) && !starts.contains(&pos) {
+            commands.spawn().insert(Wall { pos });
+        }
+    }
+    for [a, b] in &level.portals {
+        let (a, b) = (IVec2::from(*a), IVec2::from(*b));
+        if settings.in_bounds(a) && settings.in_bounds(b) {
+            commands.spawn().insert(Portal { pos: a, exit: b });
+            commands.spawn().insert(Portal { pos: b, exit: a });
+        }
+    }
+}
+
+// Two snakes start side by side in the columns every layout keeps clear, unless a custom
+// level puts them somewhere else on the stage. A snake without a spawn of its own takes the
+// other column if the other snake's spawn is on its usual one
+fn start_pos(
+    player: usize,
+    snakes: usize,
+    custom: Option<&LevelFile>,
+    settings: &GameSettings,
+) -> IVec2 {
+    let spawn = |player| {
+        custom
+            .and_then(|level| level.spawn(player))
+            .filter(|pos| settings.in_bounds(*pos))
+    };
+    if let Some(pos) = spawn(player) {
+        return pos;
+    }
+    let columns = [IVec2::new(-1, 0), IVec2::new(1, 0)];
+    match snakes {
+        1 => IVec2::ZERO,
+        _ if spawn(1 - player) == Some(columns[player]) => columns[1 - player],
+        _ => columns[player],
+    }
+}
+
+// Every `level_food` food eaten, counting both snakes, moves the game on a level
+pub(crate) fn level_up(
+    mut level: ResMut<Level>,
+    count: Res<FoodCount>,
+    settings: Res<GameSettings>,
+    mut state: ResMut<State<GameState>>,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    if settings.level_food == 0 {
+        return;
+    }
+    let reached = 1 + count.0.iter().sum::<usize>() / settings.level_food;
+    // Dying or pausing on the same move gets there first, the level up waits for the
+    // next frame or never happens
+    if reached > level.number && state.push(GameState::LevelUp).is_ok() {
+        level.number = reached;
+        audio.play(sfx.ding.clone()).with_volume(volume.sfx_gain());
+    }
+}
+
+// The board is cleared for the next layout in the cycle, or the same custom level again,
+// and the snakes start over where they first did, keeping their length, at a faster base
+// speed
+pub(crate) fn next_level(
+    mut commands: Commands,
+    mut level: ResMut<Level>,
+    mut countdown: ResMut<Countdown>,
+    mut rng: ResMut<GameRng>,
+    layout: Res<Layout>,
+    difficulty: Res<Difficulty>,
+    settings: Res<GameSettings>,
+    recording: Res<Recording>,
+    custom: Res<CustomLevel>,
+    (replay, daily): (Res<Replay>, Res<Daily>),
+    mut q_snake: Query<(
+        &mut Body,
+        &mut Steering,
+        &mut GridPos,
+        &Player,
+        &mut Effects,
+    )>,
+    mut q_grow: Query<&mut Grow>,
+    q_food: Query<Entity, With<Food>>,
+    q_power_up: Query<Entity, With<PowerUp>>,
+    q_wall: Query<Entity, InLayout>,
+) {
+    level.banner.reset();
+    countdown.0 = Timer::from_seconds(settings.countdown, false);
+
+    let speed = recording.start_speed(&settings) + settings.level_speed * (level.number - 1) as f32;
+    let speed = speed.min(settings.max_speed);
+    let snakes = q_snake.iter().len();
+    let custom = custom
+        .level()
+        .filter(|_| replay.0.is_none() && !daily.enabled);
+    for (mut body, mut steering, mut pos, player, mut effects) in &mut q_snake {
+        let start = start_pos(player.0, snakes, custom, &settings);
+        // Stacked on the start cell, it unrolls as the snake moves off
+        body.0 = VecDeque::from(vec![start; body.0.len()]);
+        *steering = Steering::new(start, speed);
+        *pos = GridPos(start);
+        effects.0.clear();
+    }
+    // Growth still owed is added at the new tail
+    for mut grow in &mut q_grow {
+        grow.pos = start_pos(grow.player, snakes, custom, &settings);
+    }
+
+    q_food.for_each(|e| commands.entity(e).despawn());
+    q_power_up.for_each(|e| commands.entity(e).despawn());
+    q_wall.for_each(|e| commands.entity(e).despawn());
+    if let Some(custom) = custom {
+        spawn_custom(&mut commands, custom, snakes, &settings);
+    } else if difficulty.walls() {
+        let i = (layout.index() + level.number - 1) % Layout::ALL.len();
+        spawn_layout(&mut commands, Layout::ALL[i], &settings, &mut rng.0);
+    }
+}
+
+// Every layout keeps the column the snake starts moving along clear
+fn wall_layout(layout: Layout, settings: &GameSettings, rng: &mut StdRng) -> Vec<IVec2> {
+    let half_stage = settings.stage_size / 2;
+    let cells: Vec<_> = match layout {
+        Layout::Random => random_walls(settings, rng),
+        // A ring around the middle with a gap in each side
+        Layout::Box => {
+            let r = half_stage.min_element() * 2 / 3;
+            (-r..=r)
+                .filter(|i| i.abs() > 2)
+                .flat_map(|i| [[i, r], [i, -r], [r, i], [-r, i]])
+                .map(IVec2::from)
+                .collect()
+        }
+        // Two diagonals meeting around the middle
+        Layout::Cross => {
+            let r = half_stage.min_element() * 3 / 4;
+            (4..=r)
+                .flat_map(|i| [[i, i], [-i, i], [i, -i], [-i, -i]])
+                .map(IVec2::from)
+                .collect()
+        }
+        // Columns open at alternating ends
+        Layout::Maze => {
+            let r = half_stage.min_element() * 3 / 4;
+            let columns = (1..=r / 5).flat_map(|c| [c * 5, -c * 5]);
+            columns
+                .flat_map(|x| {
+                    let gap = match (x / 5) % 2 == 0 {
+                        true => r - 3..=r,
+                        false => -r..=3 - r,
+                    };
+                    (-r..=r)
+                        .filter(move |y| !gap.contains(y))
+                        .map(move |y| IVec2::new(x, y))
+                })
+                .collect()
+        }
+    };
+    cells
+        .into_iter()
+        .filter(|pos| settings.in_bounds(*pos) && pos.x.abs() > 1)
+        .collect()
+}
+
+// Pairs of portal cells, clear of the walls and the start column. Random layouts have none
+fn portal_layout(layout: Layout, settings: &GameSettings) -> Vec<(IVec2, IVec2)> {
+    let half_stage = settings.stage_size / 2;
+    let pair = |a: [i32; 2], b: [i32; 2]| (IVec2::from(a), IVec2::from(b));
+    let pairs = match layout {
+        Layout::Random => vec![],
+        // Opposite corners outside the ring
+        Layout::Box => {
+            let r = half_stage.min_element() * 2 / 3 + 3;
+            vec![pair([-r, r], [r, -r])]
+        }
+        // Left and right, between the arms
+        Layout::Cross => {
+            let r = half_stage.min_element() * 3 / 4;
+            vec![pair([-r, 0], [r, 0])]
+        }
+        // Past the outer columns, so the long way round can be skipped
+        Layout::Maze => {
+            let r = half_stage.min_element() * 3 / 4;
+            vec![pair([-r - 2, r], [r + 2, -r])]
+        }
+    };
+    pairs
+        .into_iter()
+        .filter(|(a, b)| {
+            [a, b]
+                .iter()
+                .all(|p| settings.in_bounds(**p) && p.x.abs() > 1)
+        })
+        .collect()
+}
+
+fn random_walls(settings: &GameSettings, rng: &mut StdRng) -> Vec<IVec2> {
+    let half_stage = settings.stage_size / 2;
+    let mut cells = Vec::new();
+    for _ in 0..settings.wall_count {
+        let len = rng.gen_range(3..8);
+        let dir = if rng.gen_bool(0.5) {
+            IVec2::X
+        } else {
+            IVec2::Y
+        };
+        let x = rng.gen_range(1 - half_stage.x..=half_stage.x);
+        let y = rng.gen_range(1 - half_stage.y..=half_stage.y);
+        for i in 0..len {
+            let pos = IVec2::new(x, y) + dir * i;
+            if !cells.contains(&pos) {
+                cells.push(pos);
+            }
+        }
+    }
+    cells
+}
+
+fn steer_ai(
+    mut q_ai: Query<(&mut Steering, &GridPos, &AiSnake)>,
+    q_body: Query<&Body>,
+    q_food: Query<&Food, Without<Rotten>>,
+    q_wall: Query<&Wall>,
+    q_critter: Query<&Critter>,
+    settings: Res<GameSettings>,
+    wall_mode: Res<WallMode>,
+    bounds: Res<ShrinkingBounds>,
+) {
+    if q_ai.is_empty() {
+        return;
+    }
+
+    let blocked: HashSet<_> = q_body
+        .iter()
+        .flat_map(|body| body.0.iter().copied())
+        .chain(q_wall.iter().map(|wall| wall.pos))
+        .chain(q_critter.iter().map(|critter| critter.pos))
+        .collect();
+    let food: HashSet<_> = q_food
+        .iter()
+        .filter(|food| !food.poison)
+        .map(|food| food.pos)
+        .collect();
+    let open =
+        |p: IVec2| settings.in_bounds(p) && bounds.is_safe(p, &settings) && !blocked.contains(&p);
+    let step = |p: IVec2, dir: IVec2| wall_mode.wrap(p + dir, settings.stage_size);
+
+    for (mut steering, pos, ai) in &mut q_ai {
+        // Breadth first out from the head, remembering the first move of each path
+        let mut seen = HashSet::from([pos.0, steering.prev]);
+        let mut queue = VecDeque::new();
+        for dir in DIRS {
+            let next = step(pos.0, dir);
+            if open(next) && seen.insert(next) {
+                queue.push_back((next, dir, 1));
+            }
+        }
+        let mut path = None;
+        while let Some((cell, first, depth)) = queue.pop_front() {
+            if food.contains(&cell) {
+                path = Some(first);
+                break;
+            }
+            if depth == ai.lookahead {
+                continue;
+            }
+            for dir in DIRS {
+                let next = step(cell, dir);
+                if open(next) && seen.insert(next) {
+                    queue.push_back((next, first, depth + 1));
+                }
+            }
+        }
+
+        // With no food in reach keep going, or turn to whatever is free
+        let dir = path
+            .or_else(|| {
+                std::iter::once(steering.dir)
+                    .chain(DIRS)
+                    .find(|dir| step(pos.0, *dir) != steering.prev && open(step(pos.0, *dir)))
+            })
+            .unwrap_or(steering.dir);
+        // Decided again every frame so the turn taken on the next move is never stale
+        steering.turns.clear();
+        steering.turns.push_back(dir);
+    }
+}
+
+pub(crate) fn drive(
+    time: Res<Time>,
+    mut countdown: ResMut<Countdown>,
+    mut recording: ResMut<Recording>,
+    replay: Res<Replay>,
+    settings: Res<GameSettings>,
+    wall_mode: Res<WallMode>,
+    mut stats: ResMut<RunStats>,
+    mut q_snake: Query<(&mut Body, &mut Steering, &mut GridPos, &Player, &Effects)>,
+    q_portal: Query<&Portal>,
+    mut commands: Commands,
+    mut ticks: EventWriter<TickAdvanced>,
+    practice: Res<Practice>,
+) {
+    if !countdown.0.tick(time.delta()).finished() {
+        return;
+    }
+
+    let dt = time.delta_seconds();
+
+    for (mut body, mut steering, mut pos, player, effects) in &mut q_snake {
+        if let Some(replay) = &replay.0 {
+            steering.boosting = replay.boosting_at(recording.tick);
+        }
+        // Boosting costs tail segments, so it's only possible while there is a tail to
+        // spend, and it never goes past the speed acceleration is capped at
+        let speed = match steering.boosting && body.0.len() > 1 {
+            true => (steering.speed * settings.boost_factor)
+                .min(settings.max_speed)
+                .max(steering.speed),
+            false => steering.speed,
+        };
+        let slow = match effects.has(PowerUpKind::SlowDown) {
+            true => settings.slow_factor,
+            false => 1.0,
+        };
+        // Practice moves a whole cell at a time and only when stepped
+        steering.cell_pos += match practice.enabled {
+            true if practice.step => 1.0,
+            true => 0.0,
+            false => speed * slow * dt,
+        };
+
+        if steering.cell_pos < 1.0 {
+            continue;
+        }
+
+        steering.cell_pos -= 1.0;
+        let old_dir = steering.dir;
+        if let Some(replay) = &replay.0 {
+            steering.dir = replay.turn_at(recording.tick).unwrap_or(steering.dir);
+        }
+        // Skip queued turns that wouldn't change direction so the next real one isn't delayed
+        while let Some(dir) = steering.turns.pop_front() {
+            let reversed = wall_mode.wrap(pos.0 + dir, settings.stage_size) == steering.prev;
+            if dir != steering.dir && !reversed {
+                steering.dir = dir;
+                break;
+            }
+        }
+        // Only the first snake is ever recorded, replays are single player
+        if player.0 == 0 {
+            if steering.dir != old_dir {
+                recording.record(steering.dir);
+                stats.turns += 1;
+            }
+            stats.distance += 1;
+            recording.record_boost(steering.boosting);
+            recording.tick += 1;
+            ticks.send(TickAdvanced {
+                tick: recording.tick,
+            });
+        }
+
+        // Segments come off the tail, so boosting never puts anything in the snake's way
+        if speed > steering.speed {
+            steering.boost_ticks += 1;
+            if steering.boost_ticks % settings.boost_shrink_ticks == 0 {
+                commands.spawn().insert(Shrink {
+                    segments: 1,
+                    player: player.0,
+                });
+            }
+        }
+
+        let body = &mut body.0;
+        let next = wall_mode.wrap(*body.front().unwrap() + steering.dir, settings.stage_size);
+        // Stepping into a portal comes straight out of its twin, still heading the same way
+        let next = match q_portal.iter().find(|portal| portal.pos == next) {
+            Some(portal) => wall_mode.wrap(portal.exit + steering.dir, settings.stage_size),
+            None => next,
+        };
+        steering.prev = pos.0;
+        body.push_front(next);
+        body.pop_back();
+
+        *pos = GridPos(next);
+        if player.0 == 0 {
+            stats.path.push(next);
+        }
+    }
+}
+
+pub(crate) fn track_stats(
+    time: Res<Time>,
+    countdown: Res<Countdown>,
+    q_snake: Query<(&Body, &Steering, &Player)>,
+    mut stats: ResMut<RunStats>,
+) {
+    // The stats screen is about the first player
+    if let Some((body, steering, _)) = q_snake.iter().find(|(_, _, player)| player.0 == 0) {
+        if countdown.0.finished() {
+            stats.time += time.delta_seconds();
+        }
+        stats.top_speed = stats.top_speed.max(steering.speed);
+        stats.length = body.0.len();
+    }
+}
+
+// Let loose once the food is down, so neither lands on the other. Next to nothing is done
+// on a stage that already has its critters
+pub(crate) fn spawn_critters(
+    q_critter: Query<(), With<Critter>>,
+    q_snake: Query<&Body>,
+    q_wall: Query<&Wall>,
+    q_portal: Query<&Portal>,
+    q_food: Query<&Food>,
+    difficulty: Res<Difficulty>,
+    settings: Res<GameSettings>,
+    bounds: Res<ShrinkingBounds>,
+    mut rng: ResMut<GameRng>,
+    mut commands: Commands,
+) {
+    let count = difficulty.critters();
+    if count == 0 || !q_critter.is_empty() || q_food.is_empty() {
+        return;
+    }
+    let heads: Vec<_> = q_snake.iter().filter_map(|body| body.0.front()).collect();
+    let free: Vec<_> = settings
+        .stage_cells()
+        .filter(|pos| bounds.is_safe(*pos, &settings) && pos.x.abs() > 1)
+        .filter(|pos| {
+            heads
+                .iter()
+                .all(|head| (*pos - **head).abs().max_element() >= CRITTER_DISTANCE)
+        })
+        .filter(|pos| !q_snake.iter().any(|body| body.0.contains(pos)))
+        .filter(|pos| !q_wall.iter().any(|wall| wall.pos == *pos))
+        .filter(|pos| !q_portal.iter().any(|portal| portal.pos == *pos))
+        .filter(|pos| !q_food.iter().any(|food| food.pos == *pos))
+        .collect();
+    let cells = free.choose_multiple(&mut rng.0, count);
+    for (i, &pos) in cells.enumerate() {
+        let kind = match i % 2 {
+            0 => CritterKind::Rat,
+            _ => CritterKind::Spider,
+        };
+        commands.spawn().insert(Critter {
+            pos,
+            kind,
+            dir: IVec2::Y,
+        });
+    }
+}
+
+// Critters step every `critter_ticks` moves of the first snake and draw from the game's
+// rng, so replays see them go the same way. They keep off the snakes, and one a head ran
+// into this move stays put for `die` to find
+pub(crate) fn roam_critters(
+    mut ticks: EventReader<TickAdvanced>,
+    settings: Res<GameSettings>,
+    bounds: Res<ShrinkingBounds>,
+    mut rng: ResMut<GameRng>,
+    mut q_critter: Query<&mut Critter>,
+    q_body: Query<&Body>,
+    q_wall: Query<&Wall>,
+    q_portal: Query<&Portal>,
+    q_food: Query<(Entity, &Food)>,
+    mut commands: Commands,
+) {
+    let tick = match ticks.iter().last() {
+        Some(advanced) => advanced.tick,
+        None => return,
+    };
+    if tick == 0 || !tick.is_multiple_of(settings.critter_ticks.max(1)) {
+        return;
+    }
+
+    let body: HashSet<_> = q_body
+        .iter()
+        .flat_map(|body| body.0.iter().copied())
+        .collect();
+    let walls: HashSet<_> = q_wall
+        .iter()
+        .map(|wall| wall.pos)
+        .chain(q_portal.iter().map(|portal| portal.pos))
+        .collect();
+    let mut food: Vec<_> = q_food.iter().map(|(e, food)| (e, food.pos)).collect();
+    let mut taken: HashSet<_> = q_critter.iter().map(|critter| critter.pos).collect();
+    for mut critter in &mut q_critter {
+        if body.contains(&critter.pos) {
+            continue;
+        }
+        let rat = critter.kind == CritterKind::Rat;
+        let open: Vec<_> = DIRS
+            .into_iter()
+            .filter(|dir| {
+                let p = critter.pos + *dir;
+                settings.in_bounds(p)
+                    && bounds.is_safe(p, &settings)
+                    && !body.contains(&p)
+                    && !walls.contains(&p)
+                    && !taken.contains(&p)
+                    && (rat || !food.iter().any(|(_, pos)| *pos == p))
+            })
+            .collect();
+        let nearest_food = |p: IVec2| {
+            food.iter()
+                .map(|(_, pos)| (*pos - p).abs().max_element())
+                .min()
+        };
+        let dir = match critter.kind {
+            CritterKind::Rat if !food.is_empty() => open
+                .iter()
+                .copied()
+                .min_by_key(|dir| nearest_food(critter.pos + *dir)),
+            _ if open.contains(&critter.dir) && !rng.0.gen_bool(SPIDER_TURN_CHANCE) => {
+                Some(critter.dir)
+            }
+            _ => open.choose(&mut rng.0).copied(),
+        };
+        let dir = match dir {
+            Some(dir) => dir,
+            None => continue,
+        };
+
+        taken.remove(&critter.pos);
+        critter.pos += dir;
+        critter.dir = dir;
+        taken.insert(critter.pos);
+        if let Some(i) = food.iter().position(|(_, pos)| *pos == critter.pos) {
+            commands.entity(food.remove(i).0).despawn();
+        }
+    }
+}
+
+// Counts down the active power-ups on every move and picks up the one under the head
+fn apply_effects(
+    mut q_snake: Query<(&GridPos, &Player, &mut Effects), Changed<GridPos>>,
+    q_power_up: Query<(Entity, &PowerUp)>,
+    mut commands: Commands,
+    mut taken: EventWriter<PowerUpTaken>,
+    settings: Res<GameSettings>,
+) {
+    for (pos, player, mut effects) in &mut q_snake {
+        for (_, ticks) in &mut effects.0 {
+            *ticks -= 1;
+        }
+        effects.0.retain(|(_, ticks)| *ticks > 0);
+
+        for (entity, power_up) in &q_power_up {
+            if power_up.pos != pos.0 {
+                continue;
+            }
+
+            commands.entity(entity).despawn();
+            taken.send(PowerUpTaken {
+                player: player.0,
+                pos: power_up.pos,
+                kind: power_up.kind,
+            });
+            if power_up.kind == PowerUpKind::Shrink {
+                commands.spawn().insert(Shrink {
+                    segments: settings.power_up_shrink,
+                    player: player.0,
+                });
+                continue;
+            }
+            // Picking up one that is already active starts it over
+            effects.0.retain(|(active, _)| *active != power_up.kind);
+            effects.0.push((power_up.kind, settings.power_up_ticks));
+        }
+    }
+}
+
+pub(crate) fn grow(
+    mut q_grow: Query<(Entity, &mut Grow)>,
+    mut q_snake: Query<(&mut Body, &Player), Changed<GridPos>>,
+    mut commands: Commands,
+    mut grew: EventWriter<SnakeGrew>,
+    count: Res<FoodCount>,
+) {
+    for (mut body, player) in &mut q_snake {
+        let mine = q_grow
+            .iter_mut()
+            .filter(|(_, grow)| grow.player == player.0);
+        for (entity, mut grow) in mine {
+            if grow.turns <= count.0[player.0] {
+                body.0.push_back(grow.pos);
+                grew.send(SnakeGrew {
+                    player: player.0,
+                    length: body.0.len(),
+                });
+            }
+
+            grow.turns -= 1;
+
+            if grow.turns == 0 {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+pub(crate) fn shrink(
+    q_shrink: Query<(Entity, &Shrink)>,
+    mut q_snake: Query<(&mut Body, &Player), Changed<GridPos>>,
+    mut commands: Commands,
+) {
+    for (mut body, player) in &mut q_snake {
+        let mine = q_shrink
+            .iter()
+            .filter(|(_, shrink)| shrink.player == player.0);
+        for (entity, shrink) in mine {
+            for _ in 0..shrink.segments {
+                body.0.pop_back();
+            }
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Only takes the board down, ending the game and everything that goes with a crash is
+// left to whoever reads `SnakeDied`
+pub(crate) fn die(
+    q_moved: Query<(&GridPos, &Body, &Player, &Effects), Changed<GridPos>>,
+    q_snake: Query<(Entity, &Body, &Player)>,
+    q_food: Query<Entity, With<Food>>,
+    q_power_up: Query<Entity, With<PowerUp>>,
+    q_wall: Query<&Wall>,
+    q_critter: Query<&Critter>,
+    mut commands: Commands,
+    mut died: EventWriter<SnakeDied>,
+    mut bonked: EventWriter<Bonk>,
+    settings: Res<GameSettings>,
+    wall_mode: Res<WallMode>,
+    bounds: Res<ShrinkingBounds>,
+    zen: Res<Zen>,
+    mut bonking: Local<[bool; 2]>,
+) {
+    let mut dead = Vec::new();
+    for (pos, body, player, effects) in &q_moved {
+        // The tail can stack on a single cell while growing, so only end the game once.
+        // A snake starting a new level is stacked under its own head, which doesn't count
+        let hit_self = body
+            .0
+            .iter()
+            .skip(1)
+            .skip_while(|p| **p == pos.0)
+            .any(|p| *p == pos.0)
+            && !effects.has(PowerUpKind::Ghost);
+        // Running into the other snake anywhere, heads meeting take out both
+        let hit_other = q_snake
+            .iter()
+            .any(|(_, other, p)| p != player && other.0.contains(&pos.0));
+        let hit_edge = *wall_mode == WallMode::Solid && !settings.in_bounds(pos.0);
+        let hit_wall = q_wall.iter().any(|wall| wall.pos == pos.0);
+        let hit_hazard = !bounds.is_safe(pos.0, &settings);
+        let hit_critter = q_critter.iter().any(|critter| critter.pos == pos.0);
+        let starved = body.0.is_empty();
+        let cause = [
+            (starved, DeathCause::Starved),
+            (hit_self, DeathCause::Body),
+            (hit_other, DeathCause::OtherSnake),
+            (hit_critter, DeathCause::Critter),
+            (hit_edge, DeathCause::Edge),
+            (hit_wall, DeathCause::Wall),
+            (hit_hazard, DeathCause::Hazard),
+        ]
+        .into_iter()
+        .find_map(|(hit, cause)| hit.then_some(cause));
+        let was_bonking = std::mem::replace(&mut bonking[player.0], false);
+        match cause {
+            // Zen games go on through anything but running out of snake
+            Some(cause) if zen.enabled && cause != DeathCause::Starved => {
+                bonking[player.0] = true;
+                if !was_bonking {
+                    bonked.send(Bonk {
+                        player: player.0,
+                        pos: pos.0,
+                    });
+                }
+            }
+            Some(cause) => dead.push(SnakeDied {
+                player: player.0,
+                pos: pos.0,
+                cause,
+            }),
+            None => {}
+        }
+    }
+    if dead.is_empty() {
+        return;
+    }
+
+    died.send_batch(dead.into_iter());
+    q_snake.for_each(|(e, _, _)| commands.entity(e).despawn());
+    q_food.for_each(|e| commands.entity(e).despawn());
+    q_power_up.for_each(|e| commands.entity(e).despawn());
+}
+
+fn speed_up(
+    mut eaten: EventReader<FoodEaten>,
+    mut q_snake: Query<(&Player, &mut Steering)>,
+    settings: Res<GameSettings>,
+) {
+    for food in eaten.iter().filter(|food| !food.spoiled) {
+        for (_, mut steering) in q_snake.iter_mut().filter(|(p, _)| p.0 == food.player) {
+            steering.speed = (steering.speed + settings.acceleration).min(settings.max_speed);
+        }
+    }
+}
+
+// True if a wall or a body segment is next to the head, ignoring the neck
+pub(crate) fn is_risky(
+    body: &VecDeque<IVec2>,
+    walls: &[IVec2],
+    settings: &GameSettings,
+    wall_mode: WallMode,
+) -> bool {
+    let head = body[0];
+    let neck = body.get(1).copied();
+    [IVec2::X, -IVec2::X, IVec2::Y, -IVec2::Y]
+        .into_iter()
+        .map(|dir| wall_mode.wrap(head + dir, settings.stage_size))
+        .filter(|p| Some(*p) != neck)
+        .any(|p| !settings.in_bounds(p) || walls.contains(&p) || body.contains(&p))
+}
@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+
+use crate::audio::Sounds;
+use crate::food::{Food, PowerUp};
+use crate::high_score;
+use crate::locale::{self, Localization};
+use crate::render::Theme;
+use crate::replay::Replay;
+use crate::snake::{Body, SnakeGrew};
+use crate::volume::AudioSettings;
+use crate::{juice, GameSettings, GameState, RunStats};
+
+const FILE_NAME: &str = "splits";
+// Width of the speed in the hud, which the splits are drawn over
+const SPLIT_WIDTH: i32 = 20;
+
+// Racing to `speedrun_length` segments, timed from the end of the countdown. A split is
+// taken every time food is eaten and one more when the run finishes
+#[derive(Default)]
+pub struct Speedrun {
+    // Set from the mode picked on the title screen when each game starts
+    pub enabled: bool,
+    splits: Vec<f32>,
+    // Splits of the fastest finished run, kept next to the high scores
+    best: Vec<f32>,
+    finished: Option<f32>,
+    // Against the best run there was before this one finished
+    delta: Option<f32>,
+}
+
+impl Speedrun {
+    pub fn start(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.splits.clear();
+        self.finished = None;
+        self.delta = None;
+    }
+
+    // A replay can't set a new best, it only compares against it
+    fn finish(&mut self, time: f32, record: bool) {
+        self.splits.push(time);
+        self.finished = Some(time);
+        self.delta = self.best.last().map(|best| time - best);
+        if record && self.delta.is_none_or(|delta| delta < 0.0) {
+            self.best = self.splits.clone();
+            high_score::save(FILE_NAME, &self.to_text());
+        }
+    }
+
+    // Latest split and how far ahead or behind the best run it is, if that got as far
+    fn last_split(&self) -> Option<(usize, f32, Option<f32>)> {
+        let i = self.splits.len().checked_sub(1)?;
+        let time = self.splits[i];
+        Some((i + 1, time, self.best.get(i).map(|best| time - best)))
+    }
+
+    // One split per line in seconds
+    fn to_text(&self) -> String {
+        let lines: Vec<_> = self.best.iter().map(|t| format!("{:.3}", t)).collect();
+        lines.join("\n")
+    }
+
+    fn from_text(text: &str) -> Self {
+        let best = text
+            .lines()
+            .map(|line| line.trim().parse().ok())
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        Self { best, ..default() }
+    }
+}
+
+// "m:ss.mmm"
+fn clock(secs: f32) -> String {
+    let millis = (secs * 1000.0) as u32;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+// Green while ahead of the best run, red while behind
+fn delta_color(delta: f32) -> Color {
+    match delta <= 0.0 {
+        true => Color::GREEN,
+        false => Color::RED,
+    }
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct SpeedrunPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(split.after(crate::snake::track_stats))
+                .with_system(finish.after(crate::ui::end_game)),
+        );
+        // Headless runs race against nothing
+        if self.headless {
+            app.init_resource::<Speedrun>();
+            return;
+        }
+        let speedrun = high_score::load(FILE_NAME)
+            .map(|text| Speedrun::from_text(&text))
+            .unwrap_or_default();
+        app.insert_resource(speedrun)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(draw_timer.after(crate::render::render)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(draw_result.after(crate::ui::render_game_over)),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::GameOver)
+                    .with_system(draw_result.after(crate::ui::render_game_over)),
+            );
+    }
+}
+
+fn split(stats: Res<RunStats>, mut speedrun: ResMut<Speedrun>) {
+    if speedrun.enabled && speedrun.finished.is_none() && stats.food > speedrun.splits.len() {
+        speedrun.splits.push(stats.time);
+    }
+}
+
+// Ends the game like `die` does, but without the shake
+fn finish(
+    q_food: Query<Entity, With<Food>>,
+    q_power_up: Query<Entity, With<PowerUp>>,
+    q_snake: Query<Entity, With<Body>>,
+    mut grew: EventReader<SnakeGrew>,
+    stats: Res<RunStats>,
+    settings: Res<GameSettings>,
+    replay: Res<Replay>,
+    mut speedrun: ResMut<Speedrun>,
+    mut state: ResMut<State<GameState>>,
+    mut commands: Commands,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    let long_enough = grew
+        .iter()
+        .any(|grew| grew.player == 0 && grew.length >= settings.speedrun_length);
+    if !speedrun.enabled || !long_enough {
+        return;
+    }
+    // Dying on the same move gets there first
+    if state.set(GameState::GameOver).is_err() {
+        return;
+    }
+    q_snake.for_each(|e| commands.entity(e).despawn());
+    q_food.for_each(|e| commands.entity(e).despawn());
+    q_power_up.for_each(|e| commands.entity(e).despawn());
+    commands.spawn().insert(juice::Effect::flash(Color::GREEN));
+    audio.play(sfx.ding.clone()).with_volume(volume.sfx_gain());
+    speedrun.finish(stats.time, replay.0.is_none());
+}
+
+// Drawn over the time and the speed in the hud every frame, `render` only draws when the
+// snake moves
+fn draw_timer(
+    speedrun: Res<Speedrun>,
+    stats: Res<RunStats>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if !speedrun.enabled {
+        return;
+    }
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    let timer = format!(" {:>10} ", clock(stats.time));
+    term.put_string(
+        [timer.len() as i32 + 1, 0].pivot(Pivot::TopRight),
+        timer.fg(colors.text),
+    );
+
+    let (n, time, delta) = match speedrun.last_split() {
+        Some(split) => split,
+        None => return,
+    };
+    let (value, color) = match delta {
+        Some(delta) => (format!("{:+.3}", delta), delta_color(delta)),
+        None => (clock(time), colors.text),
+    };
+    let label = format!(" {} ", loc.format("hud.split", &[&format!("{:>2}", n)]));
+    let value = format!("{:>8} ", value);
+    let x = SPLIT_WIDTH + 1;
+    let value_x = x - locale::width(&label);
+    term.put_string([x, 0].pivot(Pivot::BottomRight), label.fg(colors.text));
+    term.put_string([value_x, 0].pivot(Pivot::BottomRight), value.fg(color));
+}
+
+fn draw_result(
+    speedrun: Res<Speedrun>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if !speedrun.enabled {
+        return;
+    }
+    let colors = theme.colors();
+    let (text, color) = match (speedrun.finished, speedrun.delta) {
+        (Some(time), Some(delta)) => (
+            loc.format(
+                "game_over.finished_delta",
+                &[&clock(time), &format!("{:+.3}", delta)],
+            ),
+            delta_color(delta),
+        ),
+        (Some(time), None) => (
+            loc.format("game_over.finished", &[&clock(time)]),
+            colors.highlight,
+        ),
+        (None, _) => (loc.get("game_over.did_not_finish"), colors.faded),
+    };
+    q_term.single_mut().put_string(
+        [locale::centered(&text), 6].pivot(Pivot::Center),
+        text.fg(color),
+    );
+}
@@ -0,0 +1,505 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+
+use crate::combo::Combo;
+use crate::food::{Food, FoodCount, FoodTimer, PowerUp, PowerUpKind, Rotten};
+use crate::high_score;
+use crate::replay::{Recording, Replay};
+use crate::snake::{
+    AiSnake, Body, Critter, CritterKind, Effects, GridPos, Grow, InLayout, Player, Shrink, Steering,
+};
+use crate::speedrun::Speedrun;
+use crate::{
+    Difficulty, GameSettings, GameState, Layout, Level, Players, RunStats, Score, ShrinkingBounds,
+    StageSize, WallMode,
+};
+
+const FILE_NAME: &str = "suspended";
+
+// A game put away from the pause screen, as the text it's saved as. Continuing it from the
+// title screen uses it up
+#[derive(Default)]
+pub struct SuspendedGame(pub Option<String>);
+
+// Set on the title screen for the game that's about to start
+#[derive(Default)]
+struct Resuming(bool);
+
+pub struct SuspendPlugin;
+
+impl Plugin for SuspendPlugin {
+    fn build(&self, app: &mut App) {
+        let saved = high_score::load(FILE_NAME).filter(|text| !text.is_empty());
+        app.insert_resource(SuspendedGame(saved))
+            .init_resource::<Resuming>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused).with_system(suspend.exclusive_system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Title)
+                    .with_system(continue_game.after(crate::ui::start)),
+            )
+            // After everything `spawn` put down is in the world, so it can be swapped out
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(restore.exclusive_system().at_end()),
+            );
+    }
+}
+
+// Replays have nothing to save and a speedrun's clock can't be stopped
+pub fn can_suspend(replay: &Replay, speedrun: &Speedrun) -> bool {
+    replay.0.is_none() && !speedrun.enabled
+}
+
+fn suspend(world: &mut World) {
+    let pressed = world.resource::<Input<KeyCode>>().just_pressed(KeyCode::Q);
+    if !pressed || !can_suspend(world.resource(), world.resource()) {
+        return;
+    }
+    let text = to_text(world);
+    high_score::save(FILE_NAME, &text);
+    world.resource_mut::<SuspendedGame>().0 = Some(text);
+
+    let mut q_board = world.query_filtered::<Entity, Or<(
+        With<Body>,
+        With<Food>,
+        With<PowerUp>,
+        With<Grow>,
+        With<Shrink>,
+    )>>();
+    let board: Vec<_> = q_board.iter(world).collect();
+    for entity in board {
+        world.despawn(entity);
+    }
+    world
+        .resource_mut::<State<GameState>>()
+        .replace(GameState::Title)
+        .unwrap();
+}
+
+fn continue_game(
+    input: Res<Input<KeyCode>>,
+    suspended: Res<SuspendedGame>,
+    mut resuming: ResMut<Resuming>,
+    mut replay: ResMut<Replay>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // `start` may have already queued a new game this frame
+    if input.just_pressed(KeyCode::C)
+        && suspended.0.is_some()
+        && state.set(GameState::Playing).is_ok()
+    {
+        resuming.0 = true;
+        replay.0 = None;
+    }
+}
+
+// Swaps the game `spawn` just set up for the saved one, which starts with a countdown like
+// any other. A save that can't be read is thrown away and the new game goes ahead
+fn restore(world: &mut World) {
+    if !std::mem::take(&mut world.resource_mut::<Resuming>().0) {
+        return;
+    }
+    let saved = world.resource_mut::<SuspendedGame>().0.take();
+    high_score::save(FILE_NAME, "");
+    if saved.is_some_and(|text| load(world, &text)) {
+        world.resource_mut::<RunStats>().resumed = true;
+        world.resource_mut::<Speedrun>().start(false);
+    }
+}
+
+// Puts the board back the way `to_text` found it, leaving it alone if the text can't be read
+pub(crate) fn load(world: &mut World, text: &str) -> bool {
+    let saved = match Saved::from_text(text) {
+        Some(saved) => saved,
+        None => return false,
+    };
+
+    let mut q_board = world.query_filtered::<Entity, Or<(
+        With<Body>,
+        With<Food>,
+        With<PowerUp>,
+        With<Grow>,
+        With<Shrink>,
+        InLayout,
+    )>>();
+    let board: Vec<_> = q_board.iter(world).collect();
+    for entity in board {
+        world.despawn(entity);
+    }
+
+    let mut difficulty = *world.resource::<Difficulty>();
+    let stage_size = *world.resource::<StageSize>();
+    let mut settings = world.resource_mut::<GameSettings>();
+    crate::set_difficulty(&mut difficulty, &mut settings, saved.difficulty);
+    settings.stage_size += saved.stage_size.extra_space() - stage_size.extra_space();
+    let size = (settings.stage_size + 2).as_uvec2();
+    world.insert_resource(difficulty);
+    world.insert_resource(saved.stage_size);
+    world.insert_resource(saved.players);
+    world.insert_resource(saved.layout);
+    world.insert_resource(saved.wall_mode);
+    world.insert_resource(Score(saved.score));
+    world.insert_resource(FoodCount(saved.count));
+    world.resource_mut::<Level>().number = saved.level;
+    world.insert_resource(saved.stats);
+    world.insert_resource(saved.combo);
+    world.resource_mut::<Recording>().tick = saved.tick;
+    let (enabled, rings, next) = saved.arena;
+    let mut bounds = world.resource_mut::<ShrinkingBounds>();
+    bounds.enabled = enabled;
+    bounds.rings = rings;
+    bounds.next = next;
+    bounds.tick = saved.tick;
+    for mut term in world.query::<&mut Terminal>().iter_mut(world) {
+        if term.size() != size {
+            term.resize(size);
+        }
+    }
+
+    let lookahead = difficulty.ai_lookahead();
+    for (body, steering, player, effects) in saved.snakes {
+        let head = *body.0.front().unwrap();
+        let mut snake = world.spawn();
+        snake
+            .insert(body)
+            .insert(steering)
+            .insert(GridPos(head))
+            .insert(player)
+            .insert(effects);
+        if player.0 == 1 && saved.players == Players::Computer {
+            snake.insert(AiSnake { lookahead });
+        }
+    }
+    for (food, timer, rotten) in saved.food {
+        let mut entity = world.spawn();
+        entity.insert(food);
+        if let Some(ticks) = timer {
+            entity.insert(FoodTimer(ticks));
+        }
+        if rotten {
+            entity.insert(Rotten);
+        }
+    }
+    for grow in saved.grow {
+        world.spawn().insert(grow);
+    }
+    for power_up in saved.power_ups {
+        world.spawn().insert(power_up);
+    }
+    for wall in saved.walls {
+        world.spawn().insert(wall);
+    }
+    for portal in saved.portals {
+        world.spawn().insert(portal);
+    }
+    for critter in saved.critters {
+        world.spawn().insert(critter);
+    }
+    true
+}
+
+// One line per thing on the board, starting with what it is. Cells are written "x,y" and
+// `-` stands for a timer that isn't running
+pub(crate) fn to_text(world: &mut World) -> String {
+    let stats = world.resource::<RunStats>();
+    let bounds = world.resource::<ShrinkingBounds>();
+    let combo = world.resource::<Combo>();
+    let [score, count] = [world.resource::<Score>().0, world.resource::<FoodCount>().0];
+    let mut lines = vec![
+        format!(
+            "game {:?} {:?} {:?} {:?} {:?}",
+            world.resource::<Players>(),
+            world.resource::<Difficulty>(),
+            world.resource::<Layout>(),
+            world.resource::<WallMode>(),
+            world.resource::<StageSize>(),
+        ),
+        format!("level {}", world.resource::<Level>().number),
+        format!("score {} {}", score[0], score[1]),
+        format!("count {} {}", count[0], count[1]),
+        format!(
+            "stats {} {} {} {} {} {} {}",
+            stats.time,
+            stats.top_speed,
+            stats.length,
+            stats.food,
+            stats.turns,
+            stats.distance,
+            world.resource::<Recording>().tick,
+        ),
+        format!("arena {} {} {}", bounds.enabled, bounds.rings, bounds.next),
+        format!(
+            "combo {} {} {} {} {} {}",
+            combo.multiplier[0],
+            combo.multiplier[1],
+            combo.left[0],
+            combo.left[1],
+            stats.best_combo,
+            stats.near_misses,
+        ),
+    ];
+
+    let mut q_snake = world.query::<(&Body, &Steering, &Player, &Effects)>();
+    for (body, steering, player, effects) in q_snake.iter(world) {
+        let cells: Vec<_> = body.0.iter().map(|p| cell(*p)).collect();
+        lines.push(format!(
+            "snake {} {} {} {} {} {}",
+            player.0,
+            cell(steering.dir),
+            cell(steering.prev),
+            steering.speed,
+            steering.boost_ticks,
+            cells.join(" ")
+        ));
+        for (kind, ticks) in &effects.0 {
+            lines.push(format!("effect {} {:?} {}", player.0, kind, ticks));
+        }
+    }
+    let mut q_food = world.query::<(&Food, Option<&FoodTimer>, Option<&Rotten>)>();
+    for (food, timer, rotten) in q_food.iter(world) {
+        lines.push(format!(
+            "food {} {} {} {} {} {}",
+            cell(food.pos),
+            food.value,
+            ticks(food.ttl),
+            food.poison,
+            rotten.is_some(),
+            ticks(timer.map(|timer| timer.0)),
+        ));
+    }
+    let mut q_grow = world.query::<&Grow>();
+    for grow in q_grow.iter(world) {
+        lines.push(format!(
+            "grow {} {} {}",
+            grow.player,
+            grow.turns,
+            cell(grow.pos)
+        ));
+    }
+    let mut q_power_up = world.query::<&PowerUp>();
+    for power_up in q_power_up.iter(world) {
+        lines.push(format!(
+            "power {} {:?} {}",
+            cell(power_up.pos),
+            power_up.kind,
+            power_up.ttl
+        ));
+    }
+    let mut q_wall = world.query::<&crate::snake::Wall>();
+    for wall in q_wall.iter(world) {
+        lines.push(format!("wall {}", cell(wall.pos)));
+    }
+    let mut q_portal = world.query::<&crate::snake::Portal>();
+    for portal in q_portal.iter(world) {
+        lines.push(format!("portal {} {}", cell(portal.pos), cell(portal.exit)));
+    }
+    let mut q_critter = world.query::<&Critter>();
+    for critter in q_critter.iter(world) {
+        lines.push(format!(
+            "critter {} {:?} {}",
+            cell(critter.pos),
+            critter.kind,
+            cell(critter.dir)
+        ));
+    }
+    lines.join("\n")
+}
+
+fn cell(p: IVec2) -> String {
+    format!("{},{}", p.x, p.y)
+}
+
+fn ticks(ticks: Option<usize>) -> String {
+    ticks.map_or("-".to_string(), |ticks| ticks.to_string())
+}
+
+fn parse_cell(text: &str) -> Option<IVec2> {
+    let (x, y) = text.split_once(',')?;
+    Some(IVec2::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+// Scores, combos and the controls are kept per player, there's room for two
+fn parse_player(text: &str) -> Option<usize> {
+    text.parse().ok().filter(|player| *player < 2)
+}
+
+fn parse_ticks(text: &str) -> Option<Option<usize>> {
+    match text {
+        "-" => Some(None),
+        ticks => ticks.parse().ok().map(Some),
+    }
+}
+
+fn find<T: Debug + Copy>(all: &[T], name: &str) -> Option<T> {
+    all.iter().copied().find(|t| format!("{:?}", t) == name)
+}
+
+// Everything read back from a save before any of it goes into the world
+#[derive(Default)]
+struct Saved {
+    players: Players,
+    difficulty: Difficulty,
+    layout: Layout,
+    wall_mode: WallMode,
+    stage_size: StageSize,
+    level: usize,
+    score: [usize; 2],
+    count: [usize; 2],
+    stats: RunStats,
+    tick: usize,
+    arena: (bool, i32, usize),
+    combo: Combo,
+    snakes: Vec<(Body, Steering, Player, Effects)>,
+    food: Vec<(Food, Option<usize>, bool)>,
+    grow: Vec<Grow>,
+    power_ups: Vec<PowerUp>,
+    walls: Vec<crate::snake::Wall>,
+    portals: Vec<crate::snake::Portal>,
+    critters: Vec<Critter>,
+}
+
+impl Saved {
+    // Lines that aren't known are skipped, anything else that can't be read fails the lot
+    fn from_text(text: &str) -> Option<Self> {
+        let mut saved = Saved {
+            level: 1,
+            ..default()
+        };
+        for line in text.lines() {
+            let mut f = line.split_whitespace();
+            match f.next()? {
+                "game" => {
+                    saved.players = find(&Players::ALL, f.next()?)?;
+                    saved.difficulty = find(&Difficulty::ALL, f.next()?)?;
+                    saved.layout = find(&Layout::ALL, f.next()?)?;
+                    saved.wall_mode = find(&WallMode::ALL, f.next()?)?;
+                    saved.stage_size = find(&StageSize::ALL, f.next()?)?;
+                }
+                "level" => saved.level = f.next()?.parse().ok()?,
+                "score" => saved.score = [f.next()?.parse().ok()?, f.next()?.parse().ok()?],
+                "count" => saved.count = [f.next()?.parse().ok()?, f.next()?.parse().ok()?],
+                "stats" => {
+                    saved.stats.time = f.next()?.parse().ok()?;
+                    saved.stats.top_speed = f.next()?.parse().ok()?;
+                    saved.stats.length = f.next()?.parse().ok()?;
+                    saved.stats.food = f.next()?.parse().ok()?;
+                    saved.stats.turns = f.next()?.parse().ok()?;
+                    saved.stats.distance = f.next()?.parse().ok()?;
+                    saved.tick = f.next()?.parse().ok()?;
+                }
+                "arena" => {
+                    saved.arena = (
+                        f.next()?.parse().ok()?,
+                        f.next()?.parse().ok()?,
+                        f.next()?.parse().ok()?,
+                    )
+                }
+                "combo" => {
+                    saved.combo.multiplier = [f.next()?.parse().ok()?, f.next()?.parse().ok()?];
+                    saved.combo.left = [f.next()?.parse().ok()?, f.next()?.parse().ok()?];
+                    saved.stats.best_combo = f.next()?.parse().ok()?;
+                    saved.stats.near_misses = f.next()?.parse().ok()?;
+                }
+                "snake" => {
+                    let player = Player(parse_player(f.next()?)?);
+                    let dir = parse_cell(f.next()?)?;
+                    let prev = parse_cell(f.next()?)?;
+                    let speed = f.next()?.parse().ok()?;
+                    let boost_ticks = f.next()?.parse().ok()?;
+                    let body: VecDeque<_> = f.map(parse_cell).collect::<Option<_>>()?;
+                    let mut steering = Steering::new(*body.front()?, speed);
+                    steering.dir = dir;
+                    steering.prev = prev;
+                    steering.boost_ticks = boost_ticks;
+                    saved
+                        .snakes
+                        .push((Body(body), steering, player, Effects::default()));
+                }
+                "effect" => {
+                    let player = parse_player(f.next()?)?;
+                    let kind = find(&PowerUpKind::ALL, f.next()?)?;
+                    let ticks = f.next()?.parse().ok()?;
+                    let (_, _, _, effects) =
+                        saved.snakes.iter_mut().find(|(_, _, p, _)| p.0 == player)?;
+                    effects.0.push((kind, ticks));
+                }
+                "food" => {
+                    let food = Food {
+                        pos: parse_cell(f.next()?)?,
+                        value: f.next()?.parse().ok()?,
+                        ttl: parse_ticks(f.next()?)?,
+                        poison: f.next()?.parse().ok()?,
+                    };
+                    let rotten = f.next()?.parse().ok()?;
+                    let timer = parse_ticks(f.next()?)?;
+                    saved.food.push((food, timer, rotten));
+                }
+                "grow" => saved.grow.push(Grow {
+                    player: parse_player(f.next()?)?,
+                    turns: f.next()?.parse().ok()?,
+                    pos: parse_cell(f.next()?)?,
+                }),
+                "power" => saved.power_ups.push(PowerUp {
+                    pos: parse_cell(f.next()?)?,
+                    kind: find(&PowerUpKind::ALL, f.next()?)?,
+                    ttl: f.next()?.parse().ok()?,
+                }),
+                "wall" => saved.walls.push(crate::snake::Wall {
+                    pos: parse_cell(f.next()?)?,
+                }),
+                "portal" => saved.portals.push(crate::snake::Portal {
+                    pos: parse_cell(f.next()?)?,
+                    exit: parse_cell(f.next()?)?,
+                }),
+                "critter" => saved.critters.push(Critter {
+                    pos: parse_cell(f.next()?)?,
+                    kind: find(&CritterKind::ALL, f.next()?)?,
+                    dir: parse_cell(f.next()?)?,
+                }),
+                _ => {}
+            }
+        }
+        // A game without a snake isn't worth carrying on
+        (!saved.snakes.is_empty()).then_some(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{headless_app, step};
+
+    #[test]
+    fn saved_games_load_as_they_were() {
+        let mut app = headless_app();
+        app.update();
+        for _ in 0..20 {
+            step(&mut app, KeyCode::Space);
+        }
+        let text = to_text(&mut app.world);
+
+        let mut app = headless_app();
+        app.update();
+        assert!(load(&mut app.world, &text));
+        assert_eq!(to_text(&mut app.world), text);
+    }
+
+    #[test]
+    fn snakes_past_the_second_player_are_rejected() {
+        let mut app = headless_app();
+        app.update();
+        let text = to_text(&mut app.world);
+        let bad = text.replacen("snake 0 ", "snake 2 ", 1);
+        assert_ne!(text, bad);
+        assert!(!load(&mut app.world, &bad));
+        assert!(!load(
+            &mut app.world,
+            &text.replacen("snake 0 ", "snake x ", 1)
+        ));
+    }
+}
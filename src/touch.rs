@@ -0,0 +1,131 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use bevy_ascii_terminal::ToWorld;
+use serde::{Deserialize, Serialize};
+
+use crate::GameState;
+
+// Logical pixels a finger has to travel for a swipe, anything shorter is a tap
+const SWIPE_DISTANCE: f32 = 30.0;
+// Tiles from the center of the d-pad to its arrows
+const DPAD_REACH: i32 = 2;
+
+// How touches steer, picked on the options screen
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchMode {
+    #[default]
+    Swipe,
+    // Arrows drawn in the bottom right corner of the terminal
+    DPad,
+}
+
+impl TouchMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            TouchMode::Swipe => TouchMode::DPad,
+            TouchMode::DPad => TouchMode::Swipe,
+        }
+    }
+}
+
+// What the touch screen did this frame, read by `Controls` along with the keys
+#[derive(Default)]
+pub struct TouchInput {
+    pub dir: IVec2,
+    // Touched and let go without swiping or hitting the d-pad
+    pub tap: bool,
+}
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchMode>()
+            .init_resource::<TouchInput>()
+            .add_system_to_stage(CoreStage::PreUpdate, read_touches.after(InputSystem))
+            .add_system_to_stage(CoreStage::PostUpdate, draw_dpad);
+    }
+}
+
+fn read_touches(
+    touches: Res<Touches>,
+    mode: Res<TouchMode>,
+    windows: Res<Windows>,
+    q_term: Query<(&Terminal, &ToWorld)>,
+    mut input: ResMut<TouchInput>,
+) {
+    *input = TouchInput::default();
+    let height = windows.get_primary().map_or(0.0, |window| window.height());
+    match *mode {
+        TouchMode::Swipe => {
+            for touch in touches.iter_just_released() {
+                let distance =
+                    flip(touch.position(), height) - flip(touch.start_position(), height);
+                match distance.length() < SWIPE_DISTANCE {
+                    true => input.tap = true,
+                    false => input.dir = major_axis(distance),
+                }
+            }
+        }
+        TouchMode::DPad => {
+            let (term, to_world) = match q_term.get_single() {
+                Ok(term) => term,
+                Err(_) => return,
+            };
+            for touch in touches.iter_just_pressed() {
+                let pressed = to_world
+                    .screen_to_world(flip(touch.position(), height))
+                    .map(|world| to_world.world_to_tile(world) - dpad_center(term));
+                match pressed {
+                    Some(d) if d != IVec2::ZERO && d.abs().max_element() <= DPAD_REACH => {
+                        input.dir = major_axis(d.as_vec2())
+                    }
+                    _ => input.tap = true,
+                }
+            }
+        }
+    }
+}
+
+// Touches come in from the top of the window on the web and desktop, the camera counts
+// from the bottom. Bevy already turns them around on phones
+fn flip(pos: Vec2, height: f32) -> Vec2 {
+    match cfg!(target_os = "android") || cfg!(target_os = "ios") {
+        true => pos,
+        false => Vec2::new(pos.x, height - pos.y),
+    }
+}
+
+// Diagonals go sideways
+fn major_axis(v: Vec2) -> IVec2 {
+    match v.x.abs() >= v.y.abs() {
+        true => IVec2::new(v.x.signum() as i32, 0),
+        false => IVec2::new(0, v.y.signum() as i32),
+    }
+}
+
+// In tile coordinates, leaving the border clear
+fn dpad_center(term: &Terminal) -> IVec2 {
+    IVec2::new(term.size().x as i32 - 2 - DPAD_REACH, 1 + DPAD_REACH)
+}
+
+// Drawn over the stage after it's rendered, only while a game is on
+fn draw_dpad(mode: Res<TouchMode>, state: Res<State<GameState>>, mut q_term: Query<&mut Terminal>) {
+    if *mode != TouchMode::DPad || state.current() != &GameState::Playing {
+        return;
+    }
+    let mut term = match q_term.get_single_mut() {
+        Ok(term) => term,
+        Err(_) => return,
+    };
+    let center = dpad_center(&term);
+    for (glyph, dir) in [
+        ('▲', IVec2::Y),
+        ('▼', IVec2::NEG_Y),
+        ('◄', IVec2::NEG_X),
+        ('►', IVec2::X),
+    ] {
+        term.put_char(center + dir * DPAD_REACH, glyph.fg(Color::GRAY));
+    }
+}
@@ -0,0 +1,1137 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ascii_terminal::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+
+use crate::accessibility::Accessibility;
+use crate::audio::Sounds;
+use crate::controls::{Action, Controls, KeyBindings, BINDABLE};
+use crate::crash::{self, Wreck};
+use crate::daily::Daily;
+use crate::editor::CustomLevel;
+use crate::high_score::{Ghost, HighScores, Totals};
+use crate::input::Demo;
+use crate::juice::ScreenShake;
+use crate::locale::{self, Localization};
+use crate::options::{self, Options};
+use crate::render::{fit_menu, Colors, Theme};
+use crate::replay::{self, Recording, Replay, ReplayFile};
+use crate::snake::{Body, Player, SnakeDied};
+use crate::speedrun::Speedrun;
+use crate::suspend::{self, SuspendedGame};
+use crate::touch::TouchMode;
+use crate::volume::{self, AudioSettings};
+use crate::zen::Zen;
+use crate::{
+    set_difficulty, AutoRestart, Difficulty, GameMode, GameSettings, GameState, Layout, Level,
+    Players, RunStats, Score, StageSize, WallMode, Winner,
+};
+
+const MIN_NAME: usize = 3;
+pub(crate) const MAX_NAME: usize = 8;
+const CURSOR_BLINK: f32 = 0.4;
+// What the arrow keys cycle through, typing is held to the same characters
+const NAME_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+// The name being typed for a high score, kept between runs so the next one starts with it
+#[derive(Default)]
+pub struct NameEntry {
+    pub name: Vec<char>,
+    // Between 0 and the length of the name, on the character that typing replaces
+    pub cursor: usize,
+}
+
+impl NameEntry {
+    // Replaces the character under the cursor or adds one at the end
+    pub fn type_char(&mut self, c: char) {
+        let c = c.to_ascii_uppercase();
+        if !NAME_CHARS.contains(c) {
+            return;
+        }
+        if self.cursor < self.name.len() {
+            self.name[self.cursor] = c;
+        } else if self.name.len() < MAX_NAME {
+            self.name.push(c);
+        } else {
+            return;
+        }
+        self.cursor += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.name.remove(self.cursor);
+        }
+    }
+
+    pub fn move_cursor(&mut self, dx: i32) {
+        self.cursor = match dx < 0 {
+            true => self.cursor.saturating_sub(1),
+            false => (self.cursor + 1).min(self.name.len()),
+        };
+    }
+
+    // Steps the character under the cursor through `NAME_CHARS`, past the end it starts a
+    // new one at 'A'
+    pub fn cycle(&mut self, dy: i32) {
+        if self.cursor == self.name.len() {
+            if self.name.len() < MAX_NAME {
+                self.name.push('A');
+            }
+            return;
+        }
+        let chars: Vec<_> = NAME_CHARS.chars().collect();
+        let i = chars
+            .iter()
+            .position(|c| *c == self.name[self.cursor])
+            .unwrap_or(0);
+        let next = (i as i32 + dy).rem_euclid(chars.len() as i32);
+        self.name[self.cursor] = chars[next as usize];
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.name.len() >= MIN_NAME
+    }
+}
+
+// Everything the title screen shows, for the systems that draw it
+#[derive(SystemParam)]
+struct TitleScreen<'w, 's> {
+    q_term: Query<'w, 's, &'static mut Terminal>,
+    wall_mode: Res<'w, WallMode>,
+    difficulty: Res<'w, Difficulty>,
+    layout: Res<'w, Layout>,
+    custom: Res<'w, CustomLevel>,
+    players: Res<'w, Players>,
+    bindings: Res<'w, KeyBindings>,
+    theme: Res<'w, Theme>,
+    stage_size: Res<'w, StageSize>,
+    settings: Res<'w, GameSettings>,
+    mode: Res<'w, GameMode>,
+    demo: Res<'w, Demo>,
+    suspended: Res<'w, SuspendedGame>,
+    loc: Res<'w, Localization>,
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct UiPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(end_game.after(crate::snake::die)),
+        )
+        .add_system_set(SystemSet::on_update(GameState::LevelUp).with_system(level_banner));
+        if self.headless {
+            return;
+        }
+        app.init_resource::<NameEntry>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Title)
+                    .with_system(fit_menu)
+                    .with_system(crate::restore_picks)
+                    .with_system(render_title.after(fit_menu).after(crate::restore_picks)),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::Title)
+                    .with_system(fit_menu)
+                    .with_system(crate::restore_picks)
+                    .with_system(render_title.after(fit_menu).after(crate::restore_picks)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Title)
+                    .with_system(render_demo)
+                    .with_system(start)
+                    .with_system(open_bindings)
+                    .with_system(open_options)
+                    .with_system(cycle_difficulty)
+                    .with_system(cycle_layout)
+                    .with_system(cycle_stage_size)
+                    .with_system(cycle_mode)
+                    .with_system(toggle_players)
+                    .with_system(watch_replay.after(start))
+                    .with_system(toggle_walls),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(fit_menu.after(crate::crash::freeze_board))
+                    .with_system(render_game_over.after(crate::record_run).after(fit_menu))
+                    .with_system(reset_auto_restart),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::GameOver)
+                    .with_system(render_game_over)
+                    .with_system(reset_auto_restart),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::TextInput).with_system(start_name_entry))
+            .add_system_set(
+                SystemSet::on_update(GameState::TextInput)
+                    .with_system(type_name)
+                    .with_system(render_name_entry.after(type_name)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(start)
+                    .with_system(auto_restart.after(start))
+                    .with_system(toggle_walls),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::LevelUp)
+                    .with_system(render_level.after(crate::snake::next_level)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Bindings).with_system(render_bindings))
+            .add_system_set(SystemSet::on_update(GameState::Bindings).with_system(rebind))
+            .add_system_set(SystemSet::on_update(GameState::Options).with_system(options))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(render_pause));
+    }
+}
+
+fn render_title(mut title: TitleScreen) {
+    title.draw();
+}
+
+// The demo goes under the menu, so it's all drawn again whenever the demo snake moves
+fn render_demo(mut title: TitleScreen) {
+    if title.demo.is_changed() {
+        title.draw();
+    }
+}
+
+impl TitleScreen<'_, '_> {
+    pub fn draw(&mut self) {
+        let TitleScreen {
+            wall_mode,
+            difficulty,
+            layout,
+            custom,
+            players,
+            bindings,
+            theme,
+            stage_size,
+            settings,
+            mode,
+            demo,
+            suspended,
+            loc,
+            ..
+        } = self;
+        let colors = theme.colors();
+        let mut term = self.q_term.single_mut();
+        term.clear();
+        term.draw_border(BorderGlyphs::single_line());
+        if let Some(head) = demo.body.front() {
+            term.put_char(demo.food, settings.food_glyph.fg(colors.faded));
+            for pos in demo.body.iter().skip(1) {
+                term.put_char(*pos, settings.body_glyph.fg(colors.faded));
+            }
+            term.put_char(*head, settings.head_glyph.fg(colors.faded));
+        }
+        draw_title_text(&mut term, colors, bindings, loc);
+        draw_difficulty(&mut term, **difficulty, loc);
+        draw_layout(&mut term, **layout, custom, loc);
+        draw_wall_mode(&mut term, **wall_mode, loc);
+        draw_players(&mut term, **players, loc);
+        draw_stage_size(&mut term, **stage_size, settings, loc);
+        draw_mode(&mut term, **mode, loc);
+        if suspended.0.is_some() {
+            let text = loc.get("title.continue");
+            term.put_string(
+                [locale::centered(&text), 0].pivot(Pivot::Center),
+                text.fg(colors.text),
+            );
+        }
+        #[cfg(feature = "leaderboard")]
+        {
+            let text = loc.get("title.leaderboard");
+            term.put_string(
+                [locale::centered(&text), -10].pivot(Pivot::Center),
+                text.fg(colors.faded),
+            );
+        }
+    }
+}
+
+fn draw_title_text(
+    term: &mut Terminal,
+    colors: Colors,
+    bindings: &KeyBindings,
+    loc: &Localization,
+) {
+    let title = loc.get("title.name");
+    // An odd width keeps the title in the middle of the box
+    let width = (locale::width(&title) + 2) | 1;
+    term.draw_box(
+        [0, 5].pivot(Pivot::Center),
+        [width as u32, 3],
+        UiBox::double_line().color_fill(colors.faded, Color::BLACK),
+    );
+    term.put_string(
+        [locale::centered(&title), 5].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    let moves: Vec<_> = Action::ALL[..4]
+        .iter()
+        .map(|action| bindings.describe(*action))
+        .collect();
+    let moves = loc.format("title.move", &[&moves.join(" ")]);
+    term.put_string(
+        [locale::centered(&moves), 2].pivot(Pivot::Center),
+        moves.fg(colors.text),
+    );
+    let begin = loc.format("title.begin", &[&bindings.describe(Action::Confirm)]);
+    term.put_string(
+        [locale::centered(&begin), 1].pivot(Pivot::Center),
+        begin.fg(colors.highlight),
+    );
+    let hints = ["title.volume", "title.menus", "title.more"];
+    for (y, hint) in (-9..=-7).rev().zip(hints) {
+        let hint = loc.get(hint);
+        term.put_string(
+            [locale::centered(&hint), y].pivot(Pivot::Center),
+            hint.fg(colors.faded),
+        );
+    }
+}
+
+// Anything typed while steering with the letter keys is thrown away, and the cursor
+// starts after the last name used
+fn start_name_entry(mut entry: ResMut<NameEntry>, mut typed: ResMut<Events<ReceivedCharacter>>) {
+    typed.clear();
+    entry.cursor = entry.name.len();
+}
+
+// Keeps only the arrow keys and typed characters, the letters are bound to steering. The
+// name is saved with Return or a confirm key once it's long enough
+fn type_name(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut typed: EventReader<ReceivedCharacter>,
+    bindings: Res<KeyBindings>,
+    mut entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for event in typed.iter() {
+        entry.type_char(event.char);
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        entry.backspace();
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        entry.move_cursor(-1);
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        entry.move_cursor(1);
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        entry.cycle(1);
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        entry.cycle(-1);
+    }
+
+    let confirm_keys: Vec<_> = bindings
+        .keys(Action::Confirm)
+        .chain([KeyCode::Return])
+        .collect();
+    if entry.is_valid() && keys.any_just_pressed(confirm_keys.iter().copied()) {
+        high_scores.name_latest(entry.name.iter().collect());
+        state.pop().unwrap();
+        // The game over screen comes back this frame, it shouldn't see the same press and
+        // restart
+        for key in confirm_keys {
+            keys.clear_just_pressed(key);
+        }
+    }
+}
+
+// A box over the game over screen, redrawn every frame for the cursor to blink
+fn render_name_entry(
+    time: Res<Time>,
+    entry: Res<NameEntry>,
+    mut q_term: Query<&mut Terminal>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.draw_box(
+        [0, 2].pivot(Pivot::Center),
+        [24, 7],
+        UiBox::double_line().filled(' '.fg(colors.faded).bg(Color::BLACK)),
+    );
+    let title = loc.get("name_entry.title");
+    term.put_string(
+        [locale::centered(&title), 4].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    // Right aligned against the name
+    let label = loc.get("name_entry.name");
+    term.put_string(
+        [-3 - locale::width(&label), 2].pivot(Pivot::Center),
+        label.fg(colors.text),
+    );
+    let cursor_on = (time.seconds_since_startup() as f32 / (CURSOR_BLINK * 2.0)).fract() < 0.5;
+    for i in 0..MAX_NAME {
+        let glyph = entry.name.get(i).copied().unwrap_or('_');
+        let tile = match (i == entry.cursor && cursor_on, i < entry.name.len()) {
+            (true, _) => glyph.fg(Color::BLACK).bg(colors.highlight),
+            (false, true) => glyph.fg(colors.highlight).bg(Color::BLACK),
+            (false, false) => glyph.fg(colors.faded).bg(Color::BLACK),
+        };
+        term.put_char([-2 + i as i32, 2].pivot(Pivot::Center), tile);
+    }
+    let hint = match entry.is_valid() {
+        true => loc.get("name_entry.save"),
+        false => loc.format("name_entry.too_short", &[&MIN_NAME]),
+    };
+    term.put_string(
+        [locale::centered(&hint), 0].pivot(Pivot::Center),
+        hint.fg(colors.faded),
+    );
+}
+
+pub(crate) fn render_game_over(
+    mut q_term: Query<&mut Terminal>,
+    high_scores: Res<HighScores>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    recording: Res<Recording>,
+    players: Res<Players>,
+    winner: Res<Winner>,
+    wall_mode: Res<WallMode>,
+    bindings: Res<KeyBindings>,
+    theme: Res<Theme>,
+    level: Res<Level>,
+    totals: Res<Totals>,
+    loc: Res<Localization>,
+    wreck: Res<Wreck>,
+    daily: Res<Daily>,
+    zen: Res<Zen>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    crash::draw_wreck(&mut term, &wreck, colors.poison);
+    let (title, color) = match stats.won {
+        true => (loc.get("game_over.won"), colors.highlight),
+        false => (loc.get("game_over.title"), colors.title),
+    };
+    term.put_string(
+        [locale::centered(&title), 7].pivot(Pivot::Center),
+        title.fg(color),
+    );
+    // Two player games say who won instead
+    if let (Players::One, Some(death)) = (*players, stats.death) {
+        let death = loc.name("death", death);
+        term.put_string(
+            [locale::centered(&death), 6].pivot(Pivot::Center),
+            death.fg(colors.faded),
+        );
+    }
+    // Passing it to `--seed` plays the same walls and food again, daily games say which
+    // day's they were
+    let seed = match (daily.enabled, daily.scored) {
+        (false, _) => loc.format("game_over.seed", &[&recording.seed]),
+        (true, true) => loc.format("game_over.daily", &[&daily.date()]),
+        (true, false) => loc.format("game_over.daily_retry", &[&daily.date()]),
+    };
+    term.put_string(
+        [locale::centered(&seed), -4].pivot(Pivot::Center),
+        seed.fg(colors.faded),
+    );
+    let secs = stats.time as u32;
+    let time = format!("{}:{:02}", secs / 60, secs % 60);
+    let lines = match *players {
+        // Two columns, every row padded to the same width so they line up once centered
+        Players::One => [
+            (
+                loc.format("game_over.score", &[&score.0[0]]),
+                loc.format("game_over.length", &[&stats.length]),
+            ),
+            (
+                loc.format("game_over.time", &[&time]),
+                loc.format("game_over.level", &[&level.number]),
+            ),
+            (
+                loc.format("game_over.food", &[&stats.food]),
+                loc.format("game_over.turns", &[&stats.turns]),
+            ),
+            (
+                loc.format("game_over.distance", &[&stats.distance]),
+                loc.format("game_over.top_speed", &[&format!("{:.1}", stats.top_speed)]),
+            ),
+            (
+                loc.format("game_over.combo", &[&stats.best_combo.max(1)]),
+                loc.format("game_over.near_misses", &[&stats.near_misses]),
+            ),
+        ]
+        .map(|(left, right)| format!("{:<15}{:<16}", left, right)),
+        Players::Two | Players::Computer => [
+            match winner.0 {
+                Some(player) => loc.format("game_over.wins", &[&players.name(player, &loc)]),
+                None => loc.get("game_over.draw"),
+            },
+            loc.format(
+                "game_over.player_score",
+                &[&players.name(0, &loc), &score.0[0]],
+            ),
+            loc.format(
+                "game_over.player_score",
+                &[&players.name(1, &loc), &score.0[1]],
+            ),
+            loc.format("game_over.time", &[&time]),
+            String::new(),
+        ],
+    };
+    for (y, line) in (1..=5).rev().zip(lines) {
+        let x = locale::centered(&line);
+        term.put_string([x, y].pivot(Pivot::Center), line.fg(colors.text));
+    }
+    let restart = loc.format("game_over.restart", &[&bindings.describe(Action::Confirm)]);
+    term.put_string(
+        [locale::centered(&restart), 0].pivot(Pivot::Center),
+        restart.fg(colors.text),
+    );
+    let best = loc.format("game_over.best", &[&high_scores.best()]);
+    term.put_string(
+        [locale::centered(&best), -1].pivot(Pivot::Center),
+        best.fg(colors.text),
+    );
+    let hours = totals.time / 3600;
+    let played = format!(
+        "{}:{:02}:{:02}",
+        hours,
+        totals.time / 60 % 60,
+        totals.time % 60
+    );
+    let all_time = loc.format(
+        "game_over.all_runs",
+        &[&totals.games, &totals.food, &played],
+    );
+    term.put_string(
+        [locale::centered(&all_time), -2].pivot(Pivot::Center),
+        all_time.fg(colors.faded),
+    );
+    draw_wall_mode(&mut term, *wall_mode, &loc);
+
+    term.put_string(
+        [-20, -7].pivot(Pivot::Center),
+        loc.get("game_over.table").fg(colors.faded),
+    );
+    // The daily results in place of the table after a daily game, the newest is this one
+    // when it was scored. Zen games have their own table too
+    let (runs, latest) = match (daily.enabled, zen.enabled) {
+        (true, _) => (&high_scores.daily, daily.scored.then_some(0)),
+        (_, true) => (&high_scores.zen, zen.latest),
+        _ => (&high_scores.runs, high_scores.latest),
+    };
+    for (i, run) in runs.iter().enumerate() {
+        // Runs from before names were asked for show the day they were played instead
+        let name = match run.name.is_empty() {
+            true => run.day()[2..].to_string(),
+            false => run.name.clone(),
+        };
+        // Runs that filled the stage are starred
+        let line = format!(
+            "{:>2}{} {:>5} {:>4} {:>2}:{:02} {:<8} {:>3} {}",
+            i + 1,
+            if run.won { '*' } else { '.' },
+            run.score,
+            run.length,
+            run.time / 60,
+            run.time % 60,
+            name,
+            run.level,
+            loc.name("difficulty", run.difficulty),
+        );
+        let color = match latest == Some(i) {
+            true => colors.highlight,
+            false => colors.text,
+        };
+        term.put_string([-20, -8 - i as i32].pivot(Pivot::Center), line.fg(color));
+    }
+}
+
+// The snakes that didn't crash are still around until commands are applied. A crash wins
+// over anything else queued the same frame, like pausing
+pub(crate) fn end_game(
+    mut died: EventReader<SnakeDied>,
+    q_snake: Query<&Player, With<Body>>,
+    mut winner: ResMut<Winner>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let dead: Vec<_> = died.iter().map(|died| died.player).collect();
+    if dead.is_empty() {
+        return;
+    }
+    winner.0 = q_snake
+        .iter()
+        .map(|player| player.0)
+        .find(|player| !dead.contains(player));
+    let _ = state.overwrite_set(GameState::GameOver);
+}
+
+pub(crate) fn start(
+    controls: Controls,
+    mut state: ResMut<State<GameState>>,
+    mut restart: ResMut<AutoRestart>,
+    mut replay: ResMut<Replay>,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    if controls.confirm() && state.set(GameState::Playing).is_ok() {
+        replay.0 = None;
+        audio.play(sfx.ding.clone()).with_volume(volume.sfx_gain());
+        if let Some(timer) = &mut restart.0 {
+            timer.pause();
+        }
+    }
+}
+
+// Plays back the last recorded game, or the one given with `--replay`
+fn watch_replay(
+    input: Res<Input<KeyCode>>,
+    file: Res<ReplayFile>,
+    mut replay: ResMut<Replay>,
+    mut players: ResMut<Players>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input.just_pressed(KeyCode::R) {
+        if let Some(recording) = replay::load(&file) {
+            // `start` may have already queued a normal game this frame
+            if state.set(GameState::Playing).is_ok() {
+                replay.0 = Some(recording);
+                // Recordings only ever have the one snake
+                *players = Players::One;
+            }
+        }
+    }
+}
+
+fn reset_auto_restart(mut restart: ResMut<AutoRestart>) {
+    if let Some(timer) = &mut restart.0 {
+        timer.reset();
+        timer.unpause();
+    }
+}
+
+pub(crate) fn auto_restart(
+    time: Res<Time>,
+    mut q_term: Query<&mut Terminal>,
+    mut restart: ResMut<AutoRestart>,
+    mut state: ResMut<State<GameState>>,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+) {
+    let timer = match &mut restart.0 {
+        Some(timer) if !timer.paused() => timer,
+        _ => return,
+    };
+
+    // Starting by hand on the same frame goes first
+    if timer.tick(time.delta()).just_finished() && state.set(GameState::Playing).is_ok() {
+        audio.play(sfx.ding.clone()).with_volume(volume.sfx_gain());
+        return;
+    }
+
+    let left = (timer.duration().as_secs_f32() - timer.elapsed_secs()).ceil();
+    let hint = loc.format("game_over.restarting", &[&left]);
+    q_term.single_mut().put_string(
+        [locale::centered(&hint), -5].pivot(Pivot::Center),
+        hint.fg(theme.colors().faded),
+    );
+}
+
+// Only picked on the title screen, the choice sticks for the rest of the session
+fn cycle_difficulty(
+    input: Res<Input<KeyCode>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut settings: ResMut<GameSettings>,
+    stage_size: Res<StageSize>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Tab) {
+        let next = difficulty.next();
+        set_difficulty(&mut difficulty, &mut settings, next);
+        let mut term = q_term.single_mut();
+        draw_difficulty(&mut term, *difficulty, &loc);
+        draw_stage_size(&mut term, *stage_size, &settings, &loc);
+    }
+}
+
+// Choices on the title screen get a row each. Translations are longer or shorter than the
+// text they replace, so the whole row is cleared before it's redrawn
+const CHOICE_WIDTH: i32 = 36;
+
+fn draw_choice(term: &mut Terminal, y: i32, text: &str) {
+    term.clear_string(
+        [-CHOICE_WIDTH / 2, y].pivot(Pivot::Center),
+        CHOICE_WIDTH as usize,
+    );
+    term.put_string([locale::centered(text), y].pivot(Pivot::Center), text);
+}
+
+fn draw_difficulty(term: &mut Terminal, difficulty: Difficulty, loc: &Localization) {
+    let name = loc.name("difficulty", difficulty);
+    draw_choice(term, -1, &loc.format("title.difficulty", &[&name]));
+}
+
+// Going back from a custom level picks up the cycle where it was left
+fn cycle_layout(
+    input: Res<Input<KeyCode>>,
+    mut layout: ResMut<Layout>,
+    mut custom: ResMut<CustomLevel>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::L) {
+        match custom.0.is_some() {
+            true => custom.0 = None,
+            false => *layout = layout.next(),
+        }
+        draw_layout(&mut q_term.single_mut(), *layout, &custom, &loc);
+    }
+}
+
+fn draw_layout(term: &mut Terminal, layout: Layout, custom: &CustomLevel, loc: &Localization) {
+    let name = match &custom.0 {
+        Some((name, _)) => name.clone(),
+        None => loc.name("layout", layout),
+    };
+    draw_choice(term, -2, &loc.format("title.layout", &[&name]));
+}
+
+// Like Easy's extra room, the terminal only changes size once the game starts
+fn cycle_stage_size(
+    input: Res<Input<KeyCode>>,
+    mut stage_size: ResMut<StageSize>,
+    mut settings: ResMut<GameSettings>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Z) {
+        let next = stage_size.next();
+        settings.stage_size += next.extra_space() - stage_size.extra_space();
+        *stage_size = next;
+        draw_stage_size(&mut q_term.single_mut(), *stage_size, &settings, &loc);
+    }
+}
+
+fn draw_stage_size(
+    term: &mut Terminal,
+    stage_size: StageSize,
+    settings: &GameSettings,
+    loc: &Localization,
+) {
+    let size = settings.stage_size;
+    let name = loc.name("stage_size", stage_size);
+    draw_choice(
+        term,
+        -5,
+        &loc.format("title.stage", &[&name, &size.x, &size.y]),
+    );
+}
+
+fn cycle_mode(
+    input: Res<Input<KeyCode>>,
+    mut mode: ResMut<GameMode>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::B) {
+        *mode = mode.next();
+        draw_mode(&mut q_term.single_mut(), *mode, &loc);
+    }
+}
+
+fn draw_mode(term: &mut Terminal, mode: GameMode, loc: &Localization) {
+    let name = loc.name("mode", mode);
+    draw_choice(term, -6, &loc.format("title.mode", &[&name]));
+}
+
+fn toggle_players(
+    input: Res<Input<KeyCode>>,
+    mut players: ResMut<Players>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Key2) {
+        *players = players.next();
+        draw_players(&mut q_term.single_mut(), *players, &loc);
+    }
+}
+
+fn draw_players(term: &mut Terminal, players: Players, loc: &Localization) {
+    let key = match players {
+        Players::One => "title.players_one",
+        Players::Two => "title.players_two",
+        Players::Computer => "title.players_computer",
+    };
+    draw_choice(term, -4, &loc.get(key));
+}
+
+fn toggle_walls(
+    input: Res<Input<KeyCode>>,
+    mut wall_mode: ResMut<WallMode>,
+    loc: Res<Localization>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::T) {
+        *wall_mode = wall_mode.toggle();
+        draw_wall_mode(&mut q_term.single_mut(), *wall_mode, &loc);
+    }
+}
+
+fn draw_wall_mode(term: &mut Terminal, wall_mode: WallMode, loc: &Localization) {
+    let name = loc.name("wall_mode", wall_mode);
+    draw_choice(term, -3, &loc.format("title.walls", &[&name]));
+}
+
+// Starting a game or opening another menu with the same key press may have gone first
+fn open_bindings(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(KeyCode::K) {
+        let _ = state.push(GameState::Bindings);
+    }
+}
+
+fn open_options(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(KeyCode::O) {
+        let _ = state.push(GameState::Options);
+    }
+}
+
+// Rows of the options screen, the selected one changes with left, right or confirm. Their
+// names are under `options` in the language files
+const OPTIONS: [&str; 14] = [
+    "key_bindings",
+    "accessibility",
+    "sound",
+    "volume",
+    "effects",
+    "music",
+    "theme",
+    "players",
+    "walls",
+    "screen_shake",
+    "touch",
+    "ghost",
+    "smooth",
+    "language",
+];
+
+pub(crate) fn options(
+    mut controls: Controls,
+    input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    mut selected: Local<usize>,
+    mut audio: ResMut<AudioSettings>,
+    mut theme: ResMut<Theme>,
+    mut players: ResMut<Players>,
+    mut wall_mode: ResMut<WallMode>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut touch_mode: ResMut<TouchMode>,
+    mut ghost: ResMut<Ghost>,
+    mut loc: ResMut<Localization>,
+    accessibility: Res<Accessibility>,
+    mut q_term: Query<&mut Terminal>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        options::save(&Options {
+            palette: theme.palette,
+            players: *players,
+            wall_mode: *wall_mode,
+            screen_shake: *screen_shake,
+            touch_mode: *touch_mode,
+            show_ghost: ghost.visible,
+            smooth: theme.smooth,
+            language: loc.language,
+            shapes: accessibility.shapes,
+            speed_floor: accessibility.speed_floor,
+            announce: accessibility.announce,
+        });
+        state.pop().unwrap();
+        return;
+    }
+
+    // The arrow keys work in menus whatever the bindings are
+    let dir = controls.dir() + controls.second_dir();
+    let len = OPTIONS.len() as i32;
+    *selected = (*selected as i32 - dir.y).rem_euclid(len) as usize;
+    if dir.x != 0 || controls.confirm() {
+        match OPTIONS[*selected] {
+            "key_bindings" => {
+                let _ = state.push(GameState::Bindings);
+                return;
+            }
+            "accessibility" => {
+                let _ = state.push(GameState::Accessibility);
+                return;
+            }
+            // Saved by the volume plugin like the mute key
+            "sound" => audio.muted = !audio.muted,
+            // Sliders only move with left and right
+            "volume" => audio.volume = volume::step(audio.volume, dir.x),
+            "effects" => audio.sfx = volume::step(audio.sfx, dir.x),
+            "music" => audio.music = volume::step(audio.music, dir.x),
+            "theme" => {
+                theme.palette = theme.palette.next();
+                theme.body_colors = theme.palette.body_colors();
+            }
+            "players" => *players = players.next(),
+            "walls" => *wall_mode = wall_mode.toggle(),
+            "screen_shake" => screen_shake.0 = !screen_shake.0,
+            "touch" => *touch_mode = touch_mode.toggle(),
+            "ghost" => ghost.visible = !ghost.visible,
+            "smooth" => theme.smooth = !theme.smooth,
+            _ => loc.language = loc.language.next(),
+        }
+    }
+
+    let values = [
+        String::new(),
+        String::new(),
+        loc.on_off(!audio.muted),
+        slider(audio.volume),
+        slider(audio.sfx),
+        slider(audio.music),
+        loc.name("palette", theme.palette),
+        loc.name("players", *players),
+        loc.name("wall_mode", *wall_mode),
+        loc.on_off(screen_shake.0),
+        loc.name("touch", *touch_mode),
+        loc.on_off(ghost.visible),
+        loc.on_off(theme.smooth),
+        loc.name("language", loc.language),
+    ];
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let colors = theme.colors();
+    let title = loc.get("options.title");
+    term.put_string(
+        [locale::centered(&title), 7].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    for (i, (id, value)) in OPTIONS.iter().zip(values).enumerate() {
+        let name = loc.get(&format!("options.{}", id));
+        let line = match value.is_empty() {
+            true => name,
+            false => format!("{}: {}", name, value),
+        };
+        draw_row(&mut term, 5 - i as i32, &line, i == *selected, colors);
+    }
+    let help = loc.get("options.help");
+    term.put_string(
+        [locale::centered(&help), -10].pivot(Pivot::Center),
+        help.fg(colors.faded),
+    );
+    let back = loc.get("common.back");
+    term.put_string(
+        [locale::centered(&back), -11].pivot(Pivot::Center),
+        back.fg(colors.faded),
+    );
+}
+
+// Rows of a menu. The selected one has a marker next to it as well as its color
+pub(crate) fn draw_row(term: &mut Terminal, y: i32, line: &str, selected: bool, colors: Colors) {
+    let (marker, color) = match selected {
+        true => ('►', colors.highlight),
+        false => (' ', colors.text),
+    };
+    term.put_char([-12, y].pivot(Pivot::Center), marker.fg(color));
+    term.put_string([-10, y].pivot(Pivot::Center), line.fg(color));
+}
+
+// A bar of ten cells, one for every volume step
+fn slider(level: f32) -> String {
+    let filled = (level * 10.0).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled))
+}
+
+fn render_bindings(
+    mut q_term: Query<&mut Terminal>,
+    bindings: Res<KeyBindings>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+) {
+    draw_bindings(
+        &mut q_term.single_mut(),
+        &bindings,
+        theme.colors(),
+        &loc,
+        &loc.get("bindings.pick"),
+    );
+}
+
+// Pick an action with its number, then press the key it should use
+pub(crate) fn rebind(
+    input: Res<Input<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut state: ResMut<State<GameState>>,
+    mut selected: Local<Option<Action>>,
+    mut q_term: Query<&mut Terminal>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+) {
+    const NUMBERS: [KeyCode; 7] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+    ];
+
+    let message = match *selected {
+        // Back to the title or the options screen, whichever opened it
+        None if input.just_pressed(KeyCode::Escape) => {
+            state.pop().unwrap();
+            return;
+        }
+        None => match NUMBERS.iter().position(|key| input.just_pressed(*key)) {
+            Some(i) => {
+                *selected = Some(Action::ALL[i]);
+                loc.format("bindings.press", &[&loc.name("action", Action::ALL[i])])
+            }
+            None => return,
+        },
+        Some(_) if input.just_pressed(KeyCode::Escape) => {
+            *selected = None;
+            loc.get("bindings.pick")
+        }
+        Some(action) => match BINDABLE.iter().find(|key| input.just_pressed(**key)) {
+            Some(key) => {
+                *selected = None;
+                let key_name = format!("{:?}", key);
+                match bindings.rebind(action, *key) {
+                    Ok(()) => {
+                        loc.format("bindings.bound", &[&loc.name("action", action), &key_name])
+                    }
+                    Err(taken) => {
+                        loc.format("bindings.taken", &[&key_name, &loc.name("action", taken)])
+                    }
+                }
+            }
+            None => return,
+        },
+    };
+    draw_bindings(
+        &mut q_term.single_mut(),
+        &bindings,
+        theme.colors(),
+        &loc,
+        &message,
+    );
+}
+
+fn draw_bindings(
+    term: &mut Terminal,
+    bindings: &KeyBindings,
+    colors: Colors,
+    loc: &Localization,
+    message: &str,
+) {
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let title = loc.get("bindings.title");
+    term.put_string(
+        [locale::centered(&title), 6].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    for (i, action) in Action::ALL.iter().enumerate() {
+        let name = loc.name("action", *action);
+        let line = format!("{}. {}: {}", i + 1, name, bindings.describe(*action));
+        term.put_string(
+            [-8, 3 - i as i32].pivot(Pivot::Center),
+            line.fg(colors.text),
+        );
+    }
+    term.put_string(
+        [locale::centered(message), -5].pivot(Pivot::Center),
+        message.fg(colors.highlight),
+    );
+    let back = loc.get("common.back");
+    term.put_string(
+        [locale::centered(&back), -7].pivot(Pivot::Center),
+        back.fg(colors.faded),
+    );
+}
+
+fn render_pause(
+    mut q_term: Query<&mut Terminal>,
+    theme: Res<Theme>,
+    replay: Res<Replay>,
+    speedrun: Res<Speedrun>,
+    zen: Res<Zen>,
+    loc: Res<Localization>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    let title = loc.get("pause.title");
+    term.draw_box(
+        [0, 0].pivot(Pivot::Center),
+        [locale::width(&title) as u32 + 2, 3],
+        UiBox::double_line().color_fill(colors.faded, Color::BLACK),
+    );
+    term.put_string(
+        [locale::centered(&title), 0].pivot(Pivot::Center),
+        title.fg(colors.title),
+    );
+    let hints = [
+        (suspend::can_suspend(&replay, &speedrun), "pause.suspend"),
+        (zen.enabled, "pause.end"),
+    ];
+    let hints = hints.iter().filter(|(shown, _)| *shown);
+    for (y, (_, key)) in (2..).zip(hints) {
+        let hint = format!(" {} ", loc.get(key));
+        term.put_string(
+            [locale::centered(&hint), -y].pivot(Pivot::Center),
+            hint.fg(colors.faded),
+        );
+    }
+}
+
+fn render_level(
+    mut q_term: Query<&mut Terminal>,
+    level: Res<Level>,
+    theme: Res<Theme>,
+    loc: Res<Localization>,
+) {
+    let colors = theme.colors();
+    let mut term = q_term.single_mut();
+    term.clear();
+    term.draw_border(BorderGlyphs::single_line());
+    let text = loc.format("level.banner", &[&level.number]);
+    let width = locale::width(&text) + 4;
+    term.draw_box(
+        [0, 1].pivot(Pivot::Center),
+        [width as u32, 3],
+        UiBox::double_line().color_fill(colors.faded, Color::BLACK),
+    );
+    term.put_string(
+        [2 - width / 2, 1].pivot(Pivot::Center),
+        text.fg(colors.title),
+    );
+    let ready = loc.get("level.ready");
+    term.put_string(
+        [locale::centered(&ready), -2].pivot(Pivot::Center),
+        ready.fg(colors.text),
+    );
+}
+
+// Back to the game once the banner has been up for long enough, `redraw` puts the new
+// board on screen and the countdown starts
+fn level_banner(time: Res<Time>, mut level: ResMut<Level>, mut state: ResMut<State<GameState>>) {
+    if level.banner.tick(time.delta()).just_finished() {
+        state.pop().unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+use rand::Rng;
+
+use crate::audio::Sounds;
+use crate::food::{Food, PowerUp};
+use crate::snake::{Body, Player, Portal, SnakeGrew, Wall};
+use crate::volume::AudioSettings;
+use crate::{juice, particles, GameSettings, GameState, RunStats, ShrinkingBounds, Winner};
+
+// Seconds between fireworks on the victory screen
+const FIREWORK_TIME: f32 = 0.3;
+const FIREWORK_COLORS: [Color; 5] = [
+    Color::YELLOW,
+    Color::CYAN,
+    Color::FUCHSIA,
+    Color::ORANGE,
+    Color::GREEN,
+];
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct VictoryPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for VictoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(win.after(crate::ui::end_game)),
+        );
+        if !self.headless {
+            app.add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(celebrate))
+                .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(fireworks));
+        }
+    }
+}
+
+// Distinct cells, growth stacks up on the tail until the snake moves off it
+pub(crate) fn covered(body: &Body) -> usize {
+    body.0.iter().collect::<HashSet<_>>().len()
+}
+
+// Cells it takes to win out of the ones a snake can move through, which shrink along
+// with the arena
+pub(crate) fn fill_target(
+    settings: &GameSettings,
+    bounds: &ShrinkingBounds,
+    blocked: &[IVec2],
+) -> usize {
+    let open = settings
+        .stage_cells()
+        .filter(|pos| bounds.is_safe(*pos, settings) && !blocked.contains(pos))
+        .count();
+    ((open as f32 * settings.win_fill.clamp(0.0, 1.0)).ceil() as usize).max(1)
+}
+
+pub(crate) fn blocked_cells(q_wall: &Query<&Wall>, q_portal: &Query<&Portal>) -> Vec<IVec2> {
+    let walls = q_wall.iter().map(|wall| wall.pos);
+    walls
+        .chain(q_portal.iter().map(|portal| portal.pos))
+        .collect()
+}
+
+// Ends the game like `die` does once a snake has grown over enough of the stage
+fn win(
+    mut grew: EventReader<SnakeGrew>,
+    q_snake: Query<(Entity, &Body, &Player)>,
+    q_wall: Query<&Wall>,
+    q_portal: Query<&Portal>,
+    q_food: Query<Entity, With<Food>>,
+    q_power_up: Query<Entity, With<PowerUp>>,
+    settings: Res<GameSettings>,
+    bounds: Res<ShrinkingBounds>,
+    mut stats: ResMut<RunStats>,
+    mut winner: ResMut<Winner>,
+    mut state: ResMut<State<GameState>>,
+    mut commands: Commands,
+) {
+    let grown: Vec<_> = grew.iter().map(|grew| grew.player).collect();
+    if grown.is_empty() {
+        return;
+    }
+    let target = fill_target(&settings, &bounds, &blocked_cells(&q_wall, &q_portal));
+    let player = match q_snake
+        .iter()
+        .find(|(_, body, player)| grown.contains(&player.0) && covered(body) >= target)
+    {
+        Some((_, _, player)) => player.0,
+        None => return,
+    };
+    // Dying on the same move gets there first
+    if state.set(GameState::GameOver).is_err() {
+        return;
+    }
+    winner.0 = Some(player);
+    stats.won = true;
+    q_snake.for_each(|(e, _, _)| commands.entity(e).despawn());
+    q_food.for_each(|e| commands.entity(e).despawn());
+    q_power_up.for_each(|e| commands.entity(e).despawn());
+}
+
+fn celebrate(
+    stats: Res<RunStats>,
+    mut commands: Commands,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+) {
+    if stats.won {
+        commands.spawn().insert(juice::Effect::flash(Color::GOLD));
+        audio.play(sfx.ding.clone()).with_volume(volume.sfx_gain());
+    }
+}
+
+// Bursts of particles all over the victory screen until it's left. Like the particles
+// themselves they don't touch the game's rng
+fn fireworks(
+    time: Res<Time>,
+    stats: Res<RunStats>,
+    settings: Res<GameSettings>,
+    mut timer: Local<Option<Timer>>,
+    mut commands: Commands,
+) {
+    if !stats.won {
+        return;
+    }
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(FIREWORK_TIME, true));
+    if timer.tick(time.delta()).just_finished() {
+        let mut rng = rand::thread_rng();
+        let half = settings.stage_size / 2;
+        let pos = IVec2::new(
+            rng.gen_range(1 - half.x..=half.x),
+            rng.gen_range(1 - half.y..=half.y),
+        );
+        let color = FIREWORK_COLORS[rng.gen_range(0..FIREWORK_COLORS.len())];
+        particles::burst(&mut commands, pos, color);
+    }
+}
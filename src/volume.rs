@@ -0,0 +1,138 @@
+use bevy::prelude::{App, DetectChanges, Input, KeyCode, Plugin, Res, ResMut};
+use bevy_kira_audio::{Audio, AudioControl};
+
+const FILE_NAME: &str = "audio.txt";
+const VOLUME_STEP: f32 = 0.1;
+const MUSIC_VOLUME: f32 = 0.5;
+
+// Every level goes from 0 to 1. `volume` is the master level, the sound effects and the
+// music each play at their share of it
+pub struct AudioSettings {
+    pub volume: f32,
+    pub muted: bool,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+            music: MUSIC_VOLUME,
+            sfx: 1.0,
+        }
+    }
+}
+
+impl AudioSettings {
+    // Master volume, relative volumes are multiplied by this
+    pub fn gain(&self) -> f64 {
+        match self.muted {
+            true => 0.0,
+            false => self.volume as f64,
+        }
+    }
+
+    // Volume every sound effect should be played at
+    pub fn sfx_gain(&self) -> f64 {
+        self.gain() * self.sfx as f64
+    }
+
+    pub fn music_gain(&self) -> f64 {
+        self.gain() * self.music as f64
+    }
+}
+
+// One step up or down, for the volume keys and the sliders on the options screen
+pub fn step(level: f32, dir: i32) -> f32 {
+    (level + VOLUME_STEP * dir as f32).clamp(0.0, 1.0)
+}
+
+pub struct VolumePlugin;
+
+impl Plugin for VolumePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load()).add_system(adjust_volume);
+    }
+}
+
+// M mutes, +/- step the volume, usable from any screen. With Ctrl they zoom instead
+fn adjust_volume(
+    keys: Res<Input<KeyCode>>,
+    audio: Res<Audio>,
+    mut settings: ResMut<AudioSettings>,
+) {
+    if keys.just_pressed(KeyCode::M) {
+        settings.muted = !settings.muted;
+    }
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+    if keys.any_just_pressed([KeyCode::Equals, KeyCode::Plus, KeyCode::NumpadAdd]) {
+        settings.volume = step(settings.volume, 1);
+        settings.muted = false;
+    }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        settings.volume = step(settings.volume, -1);
+    }
+
+    if settings.is_changed() && !settings.is_added() {
+        // Sounds that are already playing follow the new volume too, the music plugin
+        // takes care of the music
+        audio.set_volume(settings.sfx_gain());
+        save(&settings);
+    }
+}
+
+// Stored next to the executable as "volume muted music sfx", anything unreadable falls back
+// to the default. Files from before music and sfx levels were added leave them at the default
+#[cfg(not(target_arch = "wasm32"))]
+fn load() -> AudioSettings {
+    let text = path().and_then(|path| std::fs::read_to_string(path).ok());
+    let mut fields = text.as_deref().unwrap_or_default().split_whitespace();
+    match (
+        fields.next().and_then(|volume| volume.parse().ok()),
+        fields.next().and_then(|muted| muted.parse().ok()),
+    ) {
+        (Some(volume), Some(muted)) => AudioSettings {
+            volume: f32::clamp(volume, 0.0, 1.0),
+            muted,
+            music: fields
+                .next()
+                .and_then(|music| music.parse().ok())
+                .map_or(MUSIC_VOLUME, |music: f32| music.clamp(0.0, 1.0)),
+            sfx: fields
+                .next()
+                .and_then(|sfx| sfx.parse().ok())
+                .map_or(1.0, |sfx: f32| sfx.clamp(0.0, 1.0)),
+        },
+        _ => AudioSettings::default(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(settings: &AudioSettings) {
+    if let Some(path) = path() {
+        let text = format!(
+            "{} {} {} {}",
+            settings.volume, settings.muted, settings.music, settings.sfx
+        );
+        std::fs::write(path, text).ok();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path() -> Option<std::path::PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(FILE_NAME))
+}
+
+// No file system on the web, settings only last for the session
+#[cfg(target_arch = "wasm32")]
+fn load() -> AudioSettings {
+    AudioSettings::default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save(_settings: &AudioSettings) {}
@@ -1,13 +1,44 @@
-use bevy::prelude::{ClearColor, Color, Msaa, NonSend, Plugin, WindowDescriptor};
-use bevy::window::WindowId;
+use bevy::prelude::{
+    default, App, Camera, ClearColor, Color, CoreStage, DetectChanges, Input, KeyCode, Msaa,
+    NonSend, ParallelSystemDescriptorCoercion, Plugin, Query, Res, ResMut, UVec2, WindowDescriptor,
+    Windows,
+};
+use bevy::render::camera::{CameraUpdateSystem, Viewport};
+use bevy::window::{WindowId, WindowMode};
 use bevy::winit::WinitWindows;
+use bevy_ascii_terminal::TiledCamera;
 use std::io::Cursor;
 use winit::window::Icon;
 
+use crate::high_score;
+
+const FILE_NAME: &str = "window";
+
+// How the game fills the window. The terminal is always drawn at a whole number of screen
+// pixels per font pixel so the glyphs stay sharp, `zoom` picks that number
+#[derive(Default, Clone, Copy)]
+pub struct WindowSettings {
+    // 0 for the biggest that fits the window, which is where it starts out
+    pub zoom: u32,
+    pub fullscreen: bool,
+}
+
+impl WindowSettings {
+    // Anything bigger than fits would cut off the edges of the stage
+    fn zoom_for(&self, window: UVec2, target: UVec2) -> u32 {
+        let fits = (window / target.max(UVec2::ONE)).min_element().max(1);
+        match self.zoom {
+            0 => fits,
+            zoom => zoom.min(fits),
+        }
+    }
+}
+
 pub struct WindowPlugin;
 
 impl Plugin for WindowPlugin {
-    fn build(&self, app: &mut bevy::prelude::App) {
+    fn build(&self, app: &mut App) {
+        let settings = load();
         app.add_startup_system(set_window_icon)
             .insert_resource(Msaa { samples: 1 })
             .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
@@ -17,20 +48,175 @@ impl Plugin for WindowPlugin {
                 title: "Ascii Snake".to_string(), // ToDo
                 canvas: Some("#bevy".to_owned()),
                 fit_canvas_to_parent: true,
+                mode: match settings.fullscreen {
+                    true => WindowMode::BorderlessFullscreen,
+                    false => WindowMode::Windowed,
+                },
                 ..Default::default()
-            });
+            })
+            .insert_resource(settings)
+            .add_system(adjust_window)
+            // The tiled camera fits its viewport to the window whenever either changes,
+            // this goes over it afterwards with the picked zoom
+            .add_system_to_stage(CoreStage::PostUpdate, zoom.before(CameraUpdateSystem));
     }
 }
 
-// Sets the icon on windows and X11
+// Sets the icon on windows and X11, other platforms ignore it
 fn set_window_icon(windows: NonSend<WinitWindows>) {
-    let primary = windows.get_window(WindowId::primary()).unwrap();
+    let primary = match windows.get_window(WindowId::primary()) {
+        Some(primary) => primary,
+        None => return,
+    };
     let icon_buf = Cursor::new(include_bytes!("../assets/icon.png"));
     if let Ok(image) = image::load(icon_buf, image::ImageFormat::Png) {
         let image = image.into_rgba8();
         let (width, height) = image.dimensions();
         let rgba = image.into_raw();
-        let icon = Icon::from_rgba(rgba, width, height).unwrap();
-        primary.set_window_icon(Some(icon));
+        if let Ok(icon) = Icon::from_rgba(rgba, width, height) {
+            primary.set_window_icon(Some(icon));
+        }
+    };
+}
+
+// F11 toggles fullscreen, Ctrl with +/- steps the zoom, usable from any screen. The keys on
+// their own are the volume
+fn adjust_window(
+    keys: Res<Input<KeyCode>>,
+    mut windows: ResMut<Windows>,
+    q_cam: Query<&TiledCamera>,
+    mut settings: ResMut<WindowSettings>,
+) {
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    if keys.just_pressed(KeyCode::F11) {
+        settings.fullscreen = !is_fullscreen(window.mode());
+        set_fullscreen(window, settings.fullscreen);
+    }
+    let dir = match keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        true if keys.any_just_pressed([KeyCode::Equals, KeyCode::Plus, KeyCode::NumpadAdd]) => 1,
+        true if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) => -1,
+        _ => 0,
+    };
+    if let (Ok(cam), true) = (q_cam.get_single(), dir != 0) {
+        let size = UVec2::new(window.physical_width(), window.physical_height());
+        let target = cam.target_resolution();
+        let fits = WindowSettings::default().zoom_for(size, target);
+        let zoom = settings.zoom_for(size, target) as i32 + dir;
+        // Back to following the window once it's as big as it goes
+        settings.zoom = match zoom as u32 >= fits {
+            true => 0,
+            false => zoom.max(1) as u32,
+        };
+    }
+
+    if settings.is_changed() && !settings.is_added() {
+        save(&settings);
+    }
+}
+
+fn zoom(
+    windows: Res<Windows>,
+    settings: Res<WindowSettings>,
+    mut q_cam: Query<(&TiledCamera, &mut Camera)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let size = UVec2::new(window.physical_width(), window.physical_height());
+    for (tiled, mut cam) in &mut q_cam {
+        let target = tiled.target_resolution();
+        let viewport = target * settings.zoom_for(size, target);
+        // Centered, or in the corner when even one pixel per pixel doesn't fit
+        let pos = (size.max(viewport) - viewport) / 2;
+        let current = cam
+            .viewport
+            .as_ref()
+            .map(|vp| (vp.physical_position, vp.physical_size));
+        if current != Some((pos, viewport)) {
+            cam.viewport = Some(Viewport {
+                physical_position: pos,
+                physical_size: viewport,
+                ..default()
+            });
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_fullscreen(mode: WindowMode) -> bool {
+    mode != WindowMode::Windowed
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn set_fullscreen(window: &mut bevy::window::Window, fullscreen: bool) {
+    window.set_mode(match fullscreen {
+        true => WindowMode::BorderlessFullscreen,
+        false => WindowMode::Windowed,
+    });
+}
+
+// The canvas is sized to fit the element around it, so that's what goes fullscreen. The
+// canvas follows it and is drawn at the size of the screen rather than stretched to it.
+// Leaving with Escape is up to the browser, so whether it's fullscreen is asked each time
+#[cfg(target_arch = "wasm32")]
+fn set_fullscreen(_window: &mut bevy::window::Window, fullscreen: bool) {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return,
     };
+    match fullscreen {
+        true => {
+            let parent = document
+                .query_selector("#bevy")
+                .ok()
+                .flatten()
+                .and_then(|canvas| canvas.parent_element());
+            if let Some(parent) = parent {
+                parent.request_fullscreen().ok();
+            }
+        }
+        false => document.exit_fullscreen(),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_fullscreen(_mode: WindowMode) -> bool {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.fullscreen_element())
+        .is_some()
+}
+
+// Stored as "zoom fullscreen" with the high scores, anything unreadable falls back to the
+// default
+fn load() -> WindowSettings {
+    let text = high_score::load(FILE_NAME).unwrap_or_default();
+    let mut fields = text.split_whitespace();
+    let settings = WindowSettings {
+        zoom: fields
+            .next()
+            .and_then(|zoom| zoom.parse().ok())
+            .unwrap_or(0),
+        fullscreen: fields
+            .next()
+            .and_then(|fullscreen| fullscreen.parse().ok())
+            .unwrap_or(false),
+    };
+    // Browsers only go fullscreen when a key or button was just pressed, it can't be picked
+    // up from last time
+    WindowSettings {
+        fullscreen: settings.fullscreen && !cfg!(target_arch = "wasm32"),
+        ..settings
+    }
+}
+
+fn save(settings: &WindowSettings) {
+    high_score::save(
+        FILE_NAME,
+        &format!("{} {}", settings.zoom, settings.fullscreen),
+    );
 }
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+
+use crate::audio::Sounds;
+use crate::juice;
+use crate::render::Theme;
+use crate::replay::Replay;
+use crate::snake::Bonk;
+use crate::volume::AudioSettings;
+use crate::{GameMode, GameSettings, GameState, WallMode};
+
+// Bonks are quieter and higher than a crash
+const BONK_VOLUME: f64 = 0.4;
+const BONK_PITCH: f64 = 1.5;
+
+// Zen mode. Nothing but running out of snake ends the game: the snakes go through walls,
+// each other and themselves with a bonk and come back round at the edges. It goes on until
+// it's ended from the pause screen, and its scores have a table of their own
+#[derive(Default)]
+pub struct Zen {
+    // Set from the mode picked on the title screen when each game starts
+    pub enabled: bool,
+    // Picked on the pause screen, the game ends once it's back on the board
+    ending: bool,
+    // Place of the last run in the zen table if it made it
+    pub latest: Option<usize>,
+}
+
+// Headless apps only get the rules, nothing is drawn or played
+#[derive(Default)]
+pub struct ZenPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for ZenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Zen>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(
+                    start_zen
+                        .after(crate::keep_picks)
+                        .before(crate::snake::spawn),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(finish_zen.after(crate::snake::die)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(end_zen));
+        if !self.headless {
+            app.add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(bonk_effects.after(crate::snake::die)),
+            );
+        }
+    }
+}
+
+// Replays don't know about zen games, the ones being watched play out as they were
+// recorded. The edges wrap whatever was picked on the title screen, which is back once the
+// game is over
+fn start_zen(
+    mode: Res<GameMode>,
+    replay: Res<Replay>,
+    mut zen: ResMut<Zen>,
+    mut wall_mode: ResMut<WallMode>,
+) {
+    zen.enabled = *mode == GameMode::Zen && replay.0.is_none();
+    zen.ending = false;
+    if zen.enabled {
+        *wall_mode = WallMode::Wrap;
+    }
+}
+
+fn bonk_effects(
+    mut bonked: EventReader<Bonk>,
+    settings: Res<GameSettings>,
+    theme: Res<Theme>,
+    audio: Res<Audio>,
+    volume: Res<AudioSettings>,
+    sfx: Res<Sounds>,
+    mut commands: Commands,
+) {
+    for bonk in bonked.iter() {
+        audio
+            .play(sfx.ouch.clone())
+            .with_volume(volume.sfx_gain() * BONK_VOLUME)
+            .with_playback_rate(BONK_PITCH);
+        let pos = settings.to_term(bonk.pos);
+        commands
+            .spawn()
+            .insert(juice::Effect::pulse(pos, theme.colors().poison));
+    }
+}
+
+// Unpausing with the same key press may have popped the state already
+fn end_zen(input: Res<Input<KeyCode>>, mut zen: ResMut<Zen>, mut state: ResMut<State<GameState>>) {
+    if zen.enabled && input.just_pressed(KeyCode::E) {
+        zen.ending = true;
+        let _ = state.pop();
+    }
+}
+
+// After the board is drawn again without the pause box, which is what stays behind the
+// game over screen. `render` draws it before `die`
+fn finish_zen(mut zen: ResMut<Zen>, mut state: ResMut<State<GameState>>) {
+    if zen.ending && state.set(GameState::GameOver).is_ok() {
+        zen.ending = false;
+    }
+}